@@ -0,0 +1,9 @@
+-- Saved searches, see `models::filter_preset`
+CREATE TABLE IF NOT EXISTS filter_preset (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    token TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_filter_preset_created_at ON filter_preset (created_at DESC);
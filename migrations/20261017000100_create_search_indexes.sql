@@ -0,0 +1,35 @@
+-- Full-text and fuzzy search on beatmapsets, see `short::query::search`
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- Weighted document of a beatmapset: title (A), artist (B), creator and
+-- source (C), tags (D). Declared IMMUTABLE so it can be indexed; the search
+-- tests it on its own, with the same arguments, to hit the index.
+CREATE OR REPLACE FUNCTION beatmapset_search_document(
+    title TEXT,
+    title_unicode TEXT,
+    artist TEXT,
+    artist_unicode TEXT,
+    creator TEXT,
+    source TEXT,
+    tags TEXT[]
+) RETURNS tsvector
+LANGUAGE sql IMMUTABLE PARALLEL SAFE AS $$
+    SELECT setweight(to_tsvector('simple', coalesce(title, '') || ' ' || coalesce(title_unicode, '')), 'A')
+        || setweight(to_tsvector('simple', coalesce(artist, '') || ' ' || coalesce(artist_unicode, '')), 'B')
+        || setweight(to_tsvector('simple', coalesce(creator, '') || ' ' || coalesce(source, '')), 'C')
+        || setweight(to_tsvector('simple', coalesce(array_to_string(tags, ' '), '')), 'D')
+$$;
+
+CREATE INDEX IF NOT EXISTS idx_beatmapset_search_document ON beatmapset USING GIN (
+    beatmapset_search_document(title, title_unicode, artist, artist_unicode, creator, source, tags)
+);
+
+-- Difficulty names, searched next to the beatmapset document
+CREATE INDEX IF NOT EXISTS idx_beatmap_difficulty_search ON beatmap USING GIN (
+    to_tsvector('simple', difficulty)
+);
+
+-- Typo-tolerant matching (`<%`, word_similarity)
+CREATE INDEX IF NOT EXISTS idx_beatmapset_title_trgm ON beatmapset USING GIN (title gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_beatmapset_artist_trgm ON beatmapset USING GIN (artist gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_beatmapset_creator_trgm ON beatmapset USING GIN (creator gin_trgm_ops);
@@ -0,0 +1,2 @@
+-- Lookups by .osu checksum (`find_*_by_checksum`)
+CREATE INDEX IF NOT EXISTS idx_rates_osu_hash ON rates (osu_hash);
@@ -1,3 +1,4 @@
+use crate::filters::FilterFieldError;
 use serde::Serialize;
 use utoipa::ToSchema;
 
@@ -27,11 +28,28 @@ impl<T> ApiResponse<T> {
     }
 }
 
+impl ApiResponse<Vec<FilterFieldError>> {
+    /// 400 response carrying every invalid filter field
+    pub fn invalid_filters(errors: Vec<FilterFieldError>) -> Self {
+        let message = errors
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join("; ");
+        Self {
+            data: Some(errors),
+            ..Self::error("400", format!("Invalid filters: {}", message))
+        }
+    }
+}
+
 #[derive(Serialize, Debug, Clone, ToSchema)]
 pub struct Pagination {
     pub page: u32,
     pub per_page: u32,
     pub total: u64,
+    pub total_pages: u32,
+    pub has_next: bool,
 }
 
 #[derive(Serialize, Debug, Clone, ToSchema)]
@@ -42,5 +60,16 @@ pub struct PaginatedResponse<T> {
     pub pagination: Pagination,
 }
 
+/// Page of a keyset-paginated search. Pass a cursor back to get the page
+/// after (`next_cursor`) or before (`prev_cursor`) this one.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct CursorPaginatedResponse<T> {
+    pub message: String,
+    pub status: String,
+    pub data: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+}
+
 #[derive(Serialize, Debug, Clone, ToSchema)]
 pub struct Empty;
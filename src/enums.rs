@@ -0,0 +1,292 @@
+use serde::{Deserialize, Serialize};
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::postgres::{PgArgumentBuffer, PgHasArrayType, PgTypeInfo, PgValueRef};
+use sqlx::{Decode, Encode, Postgres, Type};
+use std::fmt;
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+/// Error returned when a string or integer does not name a known variant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseEnumError {
+    pub kind: &'static str,
+    pub value: String,
+    pub expected: &'static [&'static str],
+}
+
+impl fmt::Display for ParseEnumError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid {} `{}`, expected one of: {}",
+            self.kind,
+            self.value,
+            self.expected.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for ParseEnumError {}
+
+/// Implements `as_str`, `Display`, `FromStr` and text-backed sqlx traits for a
+/// fieldless enum whose database representation is a lowercase string.
+macro_rules! text_enum {
+    ($name:ident, $kind:literal, { $($variant:ident => $value:literal),+ $(,)? }) => {
+        impl $name {
+            pub const ALL: &'static [$name] = &[$($name::$variant),+];
+            pub const VALUES: &'static [&'static str] = &[$($value),+];
+
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $($name::$variant => $value),+
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ParseEnumError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s {
+                    $($value => Ok($name::$variant),)+
+                    _ => Err(ParseEnumError {
+                        kind: $kind,
+                        value: s.to_string(),
+                        expected: Self::VALUES,
+                    }),
+                }
+            }
+        }
+
+        impl Type<Postgres> for $name {
+            fn type_info() -> PgTypeInfo {
+                <String as Type<Postgres>>::type_info()
+            }
+
+            fn compatible(ty: &PgTypeInfo) -> bool {
+                <String as Type<Postgres>>::compatible(ty)
+            }
+        }
+
+        impl PgHasArrayType for $name {
+            fn array_type_info() -> PgTypeInfo {
+                <String as PgHasArrayType>::array_type_info()
+            }
+        }
+
+        impl<'q> Encode<'q, Postgres> for $name {
+            fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> Result<IsNull, BoxDynError> {
+                <&str as Encode<Postgres>>::encode(self.as_str(), buf)
+            }
+        }
+
+        impl<'r> Decode<'r, Postgres> for $name {
+            fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+                let s = <&str as Decode<Postgres>>::decode(value)?;
+                Ok(s.parse()?)
+            }
+        }
+    };
+}
+
+/// Rating algorithm a `beatmap_rating` row was computed with.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RatingType {
+    #[default]
+    Osu,
+    Etterna,
+    Sunny,
+}
+
+text_enum!(RatingType, "rating type", {
+    Osu => "osu",
+    Etterna => "etterna",
+    Sunny => "sunny",
+});
+
+/// Mania skillset, matching both a `beatmap_mania_rating` column and the
+/// entries of `beatmap.main_pattern`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Skillset {
+    Stream,
+    Jumpstream,
+    Handstream,
+    Stamina,
+    Jackspeed,
+    Chordjack,
+    Technical,
+}
+
+text_enum!(Skillset, "skillset", {
+    Stream => "stream",
+    Jumpstream => "jumpstream",
+    Handstream => "handstream",
+    Stamina => "stamina",
+    Jackspeed => "jackspeed",
+    Chordjack => "chordjack",
+    Technical => "technical",
+});
+
+/// Ranking status of a beatmap on osu!.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RankStatus {
+    Graveyard,
+    Wip,
+    Pending,
+    Ranked,
+    Approved,
+    Qualified,
+    Loved,
+}
+
+text_enum!(RankStatus, "rank status", {
+    Graveyard => "graveyard",
+    Wip => "wip",
+    Pending => "pending",
+    Ranked => "ranked",
+    Approved => "approved",
+    Qualified => "qualified",
+    Loved => "loved",
+});
+
+/// osu! game mode, stored as its integer ruleset id in `beatmap.mode`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum GameMode {
+    Osu,
+    Taiko,
+    Fruits,
+    Mania,
+}
+
+impl GameMode {
+    pub const ALL: &'static [GameMode] = &[
+        GameMode::Osu,
+        GameMode::Taiko,
+        GameMode::Fruits,
+        GameMode::Mania,
+    ];
+    pub const VALUES: &'static [&'static str] = &["osu", "taiko", "fruits", "mania"];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GameMode::Osu => "osu",
+            GameMode::Taiko => "taiko",
+            GameMode::Fruits => "fruits",
+            GameMode::Mania => "mania",
+        }
+    }
+
+    /// Ruleset id as stored in `beatmap.mode`.
+    pub fn id(&self) -> i32 {
+        match self {
+            GameMode::Osu => 0,
+            GameMode::Taiko => 1,
+            GameMode::Fruits => 2,
+            GameMode::Mania => 3,
+        }
+    }
+}
+
+impl TryFrom<i32> for GameMode {
+    type Error = ParseEnumError;
+
+    fn try_from(id: i32) -> Result<Self, Self::Error> {
+        match id {
+            0 => Ok(GameMode::Osu),
+            1 => Ok(GameMode::Taiko),
+            2 => Ok(GameMode::Fruits),
+            3 => Ok(GameMode::Mania),
+            _ => Err(ParseEnumError {
+                kind: "game mode",
+                value: id.to_string(),
+                expected: &["0", "1", "2", "3"],
+            }),
+        }
+    }
+}
+
+impl fmt::Display for GameMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for GameMode {
+    type Err = ParseEnumError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "osu" => Ok(GameMode::Osu),
+            "taiko" => Ok(GameMode::Taiko),
+            "fruits" => Ok(GameMode::Fruits),
+            "mania" => Ok(GameMode::Mania),
+            _ => Err(ParseEnumError {
+                kind: "game mode",
+                value: s.to_string(),
+                expected: Self::VALUES,
+            }),
+        }
+    }
+}
+
+impl Type<Postgres> for GameMode {
+    fn type_info() -> PgTypeInfo {
+        <i32 as Type<Postgres>>::type_info()
+    }
+
+    fn compatible(ty: &PgTypeInfo) -> bool {
+        <i32 as Type<Postgres>>::compatible(ty)
+    }
+}
+
+impl<'q> Encode<'q, Postgres> for GameMode {
+    fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> Result<IsNull, BoxDynError> {
+        <i32 as Encode<Postgres>>::encode(self.id(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for GameMode {
+    fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+        let id = <i32 as Decode<Postgres>>::decode(value)?;
+        Ok(GameMode::try_from(id)?)
+    }
+}
+
+/// Decodes a column as `T`, or as `Known(None)` when it holds a value this
+/// crate does not know (e.g. added in `database-lib` first). The value is
+/// logged so the caller can skip the row instead of failing the whole query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Known<T>(pub Option<T>);
+
+impl<T: Type<Postgres>> Type<Postgres> for Known<T> {
+    fn type_info() -> PgTypeInfo {
+        T::type_info()
+    }
+
+    fn compatible(ty: &PgTypeInfo) -> bool {
+        T::compatible(ty)
+    }
+}
+
+impl<'r, T: Decode<'r, Postgres>> Decode<'r, Postgres> for Known<T> {
+    fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+        match T::decode(value) {
+            Ok(value) => Ok(Known(Some(value))),
+            Err(e) => {
+                log::warn!("skipping unknown value: {}", e);
+                Ok(Known(None))
+            }
+        }
+    }
+}
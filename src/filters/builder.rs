@@ -0,0 +1,304 @@
+use super::{
+    BeatmapFilter, BeatmapTechnicalFilter, BeatmapsetFilter, CentirateSelection, ExclusionFilter,
+    Filters, RatesFilter, RatingFilter, SkillsetCombinator, SkillsetFilter, SkillsetsFilter,
+    SortKey, SortOptions, SortOrder, TruncationPolicy,
+};
+use crate::enums::{GameMode, RankStatus, RatingType, Skillset};
+use chrono::NaiveDateTime;
+
+/// Fluent builder for [`Filters`].
+///
+/// ```
+/// use dto::enums::{GameMode, RatingType, Skillset};
+/// use dto::filters::Filters;
+///
+/// let filters = Filters::builder()
+///     .rating(RatingType::Osu, Some(3.0), None)
+///     .skillset(Skillset::Stream, Some(20.0), None)
+///     .mode(GameMode::Mania)
+///     .keycount(Some(4), Some(4))
+///     .per_page(20)
+///     .build();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FiltersBuilder {
+    filters: Filters,
+}
+
+impl FiltersBuilder {
+    fn rating_section(&mut self) -> &mut RatingFilter {
+        self.filters.rating.get_or_insert_with(Default::default)
+    }
+
+    fn skillsets_section(&mut self) -> &mut SkillsetsFilter {
+        self.filters.skillsets.get_or_insert_with(Default::default)
+    }
+
+    fn beatmap_section(&mut self) -> &mut BeatmapFilter {
+        self.filters.beatmap.get_or_insert_with(Default::default)
+    }
+
+    fn technical_section(&mut self) -> &mut BeatmapTechnicalFilter {
+        self.filters
+            .beatmap_technical
+            .get_or_insert_with(Default::default)
+    }
+
+    fn rates_section(&mut self) -> &mut RatesFilter {
+        self.filters.rates.get_or_insert_with(Default::default)
+    }
+
+    fn beatmapset_section(&mut self) -> &mut BeatmapsetFilter {
+        self.filters.beatmapset.get_or_insert_with(Default::default)
+    }
+
+    fn exclude_section(&mut self) -> &mut ExclusionFilter {
+        self.filters.exclude.get_or_insert_with(Default::default)
+    }
+
+    pub fn rating(mut self, rating_type: RatingType, min: Option<f64>, max: Option<f64>) -> Self {
+        let rating = self.rating_section();
+        rating.rating_type = Some(rating_type);
+        rating.rating_min = min;
+        rating.rating_max = max;
+        self
+    }
+
+    /// Add one skillset constraint; call repeatedly to combine several.
+    pub fn skillset(mut self, skillset: Skillset, min: Option<f64>, max: Option<f64>) -> Self {
+        self.skillsets_section().constraints.push(SkillsetFilter {
+            pattern_type: Some(skillset),
+            pattern_min: min,
+            pattern_max: max,
+        });
+        self
+    }
+
+    pub fn skillset_combinator(mut self, combinator: SkillsetCombinator) -> Self {
+        self.skillsets_section().combinator = Some(combinator);
+        self
+    }
+
+    pub fn search(mut self, term: impl Into<String>) -> Self {
+        self.beatmap_section().search_term = Some(term.into());
+        self
+    }
+
+    pub fn artist(mut self, artist: impl Into<String>) -> Self {
+        self.beatmap_section().artist = Some(artist.into());
+        self
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.beatmap_section().title = Some(title.into());
+        self
+    }
+
+    pub fn creator(mut self, creator: impl Into<String>) -> Self {
+        self.beatmap_section().creator = Some(creator.into());
+        self
+    }
+
+    pub fn total_time(mut self, min: Option<i32>, max: Option<i32>) -> Self {
+        let beatmap = self.beatmap_section();
+        beatmap.total_time_min = min;
+        beatmap.total_time_max = max;
+        self
+    }
+
+    pub fn bpm(mut self, min: Option<f64>, max: Option<f64>) -> Self {
+        let beatmap = self.beatmap_section();
+        beatmap.bpm_min = min;
+        beatmap.bpm_max = max;
+        self
+    }
+
+    pub fn od(mut self, min: Option<f64>, max: Option<f64>) -> Self {
+        let technical = self.technical_section();
+        technical.od_min = min;
+        technical.od_max = max;
+        self
+    }
+
+    pub fn status(mut self, status: RankStatus) -> Self {
+        self.technical_section().status = Some(status);
+        self
+    }
+
+    pub fn mode(mut self, mode: GameMode) -> Self {
+        self.technical_section().mode = Some(mode);
+        self
+    }
+
+    pub fn keycount(mut self, min: Option<i32>, max: Option<i32>) -> Self {
+        let technical = self.technical_section();
+        technical.keycount_min = min;
+        technical.keycount_max = max;
+        self
+    }
+
+    pub fn cs(mut self, min: Option<f64>, max: Option<f64>) -> Self {
+        let technical = self.technical_section();
+        technical.cs_min = min;
+        technical.cs_max = max;
+        self
+    }
+
+    pub fn ar(mut self, min: Option<f64>, max: Option<f64>) -> Self {
+        let technical = self.technical_section();
+        technical.ar_min = min;
+        technical.ar_max = max;
+        self
+    }
+
+    pub fn hp(mut self, min: Option<f64>, max: Option<f64>) -> Self {
+        let technical = self.technical_section();
+        technical.hp_min = min;
+        technical.hp_max = max;
+        self
+    }
+
+    pub fn max_combo(mut self, min: Option<i32>, max: Option<i32>) -> Self {
+        let technical = self.technical_section();
+        technical.max_combo_min = min;
+        technical.max_combo_max = max;
+        self
+    }
+
+    pub fn circles(mut self, min: Option<i32>, max: Option<i32>) -> Self {
+        let technical = self.technical_section();
+        technical.circles_min = min;
+        technical.circles_max = max;
+        self
+    }
+
+    pub fn sliders(mut self, min: Option<i32>, max: Option<i32>) -> Self {
+        let technical = self.technical_section();
+        technical.sliders_min = min;
+        technical.sliders_max = max;
+        self
+    }
+
+    pub fn centirate(mut self, selection: CentirateSelection) -> Self {
+        self.rates_section().centirate = Some(selection);
+        self
+    }
+
+    pub fn drain_time(mut self, min: Option<i32>, max: Option<i32>) -> Self {
+        let rates = self.rates_section();
+        rates.drain_time_min = min;
+        rates.drain_time_max = max;
+        self
+    }
+
+    pub fn has_video(mut self, value: bool) -> Self {
+        self.beatmapset_section().has_video = Some(value);
+        self
+    }
+
+    pub fn has_storyboard(mut self, value: bool) -> Self {
+        self.beatmapset_section().has_storyboard = Some(value);
+        self
+    }
+
+    pub fn explicit(mut self, value: bool) -> Self {
+        self.beatmapset_section().is_explicit = Some(value);
+        self
+    }
+
+    pub fn featured(mut self, value: bool) -> Self {
+        self.beatmapset_section().is_featured = Some(value);
+        self
+    }
+
+    pub fn source(mut self, source: impl Into<String>) -> Self {
+        self.beatmapset_section().source = Some(source.into());
+        self
+    }
+
+    pub fn source_contains(mut self, text: impl Into<String>) -> Self {
+        self.beatmapset_section().source_contains = Some(text.into());
+        self
+    }
+
+    /// Require a tag; call repeatedly to require several.
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.beatmapset_section().tags.push(tag.into());
+        self
+    }
+
+    pub fn tag_contains(mut self, text: impl Into<String>) -> Self {
+        self.beatmapset_section().tag_contains = Some(text.into());
+        self
+    }
+
+    pub fn status_changed(
+        mut self,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+    ) -> Self {
+        let beatmapset = self.beatmapset_section();
+        beatmapset.status_changed_from = from;
+        beatmapset.status_changed_to = to;
+        self
+    }
+
+    pub fn status_changed_within_days(mut self, days: i32) -> Self {
+        self.beatmapset_section().status_changed_within_days = Some(days);
+        self
+    }
+
+    pub fn exclude_creator(mut self, creator: impl Into<String>) -> Self {
+        self.exclude_section().creators.push(creator.into());
+        self
+    }
+
+    pub fn exclude_tag(mut self, tag: impl Into<String>) -> Self {
+        self.exclude_section().tags.push(tag.into());
+        self
+    }
+
+    pub fn exclude_pattern(mut self, pattern: Skillset) -> Self {
+        self.exclude_section().patterns.push(pattern);
+        self
+    }
+
+    pub fn exclude_beatmapset(mut self, osu_id: i32) -> Self {
+        self.exclude_section().beatmapset_ids.push(osu_id);
+        self
+    }
+
+    pub fn sort(mut self, key: SortKey, order: SortOrder) -> Self {
+        let sort = self.filters.sort.get_or_insert_with(SortOptions::default);
+        sort.key = Some(key);
+        sort.order = Some(order);
+        self
+    }
+
+    /// Shuffle with a fixed seed so every page comes from the same order.
+    pub fn random_sort(mut self, seed: i64) -> Self {
+        let sort = self.filters.sort.get_or_insert_with(SortOptions::default);
+        sort.key = Some(SortKey::Random);
+        sort.seed = Some(seed);
+        self
+    }
+
+    pub fn truncation(mut self, policy: TruncationPolicy) -> Self {
+        self.filters.truncation = Some(policy);
+        self
+    }
+
+    pub fn page(mut self, page: usize) -> Self {
+        self.filters.page = Some(page);
+        self
+    }
+
+    pub fn per_page(mut self, per_page: usize) -> Self {
+        self.filters.per_page = Some(per_page);
+        self
+    }
+
+    pub fn build(self) -> Filters {
+        self.filters
+    }
+}
@@ -0,0 +1,191 @@
+//! Opaque keyset cursors for beatmapset search.
+//!
+//! A cursor remembers the sort value and id of the last (or first) beatmapset
+//! of a page, so the next query resumes right after it instead of using
+//! `OFFSET`. Cursors are tied to the sort, seed and filters they were
+//! produced with.
+
+use super::{Filters, SortKey, SortOrder};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Position in a sorted search result.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SearchCursor {
+    #[serde(rename = "k")]
+    pub key: SortKey,
+    #[serde(rename = "o")]
+    pub order: SortOrder,
+    /// Sort value of the boundary beatmapset, as Postgres text. `None` for `id`.
+    #[serde(rename = "v", default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// Internal id of the boundary beatmapset, the tie-breaker.
+    pub id: i32,
+    /// `true` for a `prev_cursor`: fetch the page before the boundary.
+    #[serde(rename = "b", default)]
+    pub before: bool,
+    /// Seed of a `random` sort.
+    #[serde(rename = "s", default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    /// `Filters::fingerprint` of the search.
+    #[serde(rename = "f", default)]
+    pub filters: u64,
+}
+
+/// Error raised for a cursor that cannot be used with the given filters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CursorError {
+    Malformed(String),
+    /// The cursor was produced for another sort key, order or seed
+    SortMismatch,
+    /// The cursor was produced for other filters
+    FiltersMismatch,
+}
+
+impl fmt::Display for CursorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CursorError::Malformed(message) => write!(f, "malformed cursor: {}", message),
+            CursorError::SortMismatch => {
+                f.write_str("cursor was produced with another sort key, order or seed")
+            }
+            CursorError::FiltersMismatch => f.write_str("cursor was produced with other filters"),
+        }
+    }
+}
+
+impl std::error::Error for CursorError {}
+
+impl SearchCursor {
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn decode(token: &str) -> Result<Self, CursorError> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(token.trim())
+            .map_err(|e| CursorError::Malformed(e.to_string()))?;
+        serde_json::from_slice(&bytes).map_err(|e| CursorError::Malformed(e.to_string()))
+    }
+}
+
+/// 64-bit FNV-1a, stable across builds unlike `DefaultHasher`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+impl Filters {
+    /// Hash of everything that selects or orders results, i.e. all but
+    /// `cursor`, `page` and `per_page`.
+    pub fn fingerprint(&self) -> u64 {
+        let mut search = self.clone();
+        search.cursor = None;
+        search.page = None;
+        search.per_page = None;
+        fnv1a(search.to_query_string().as_bytes())
+    }
+
+    /// Decode `self.cursor`, checking it matches the requested sort and filters.
+    pub fn decoded_cursor(&self) -> Result<Option<SearchCursor>, CursorError> {
+        let Some(token) = self.cursor.as_deref() else {
+            return Ok(None);
+        };
+        let cursor = SearchCursor::decode(token)?;
+        let sort = self.sort.clone().unwrap_or_default();
+        if cursor.key != sort.key() || cursor.order != sort.order() || cursor.seed != sort.seed {
+            return Err(CursorError::SortMismatch);
+        }
+        if cursor.filters != self.fingerprint() {
+            return Err(CursorError::FiltersMismatch);
+        }
+        Ok(Some(cursor))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filters::SortOptions;
+
+    fn random_sort(seed: Option<i64>) -> Filters {
+        Filters {
+            sort: Some(SortOptions {
+                key: Some(SortKey::Random),
+                seed,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Cursor after the first page of `filters`, as the search would make it.
+    fn next_cursor(filters: &Filters) -> String {
+        let sort = filters.sort.clone().unwrap_or_default();
+        SearchCursor {
+            key: sort.key(),
+            order: sort.order(),
+            value: Some("0f3a".to_string()),
+            id: 12,
+            before: false,
+            seed: sort.seed,
+            filters: filters.fingerprint(),
+        }
+        .encode()
+    }
+
+    #[test]
+    fn random_follow_up_page_reuses_the_cursor_seed() {
+        let first = random_sort(None).validated().unwrap();
+        let seed = first.sort.as_ref().unwrap().seed;
+        assert!(seed.is_some());
+
+        // The client sends the cursor back without the seed
+        let mut next = random_sort(None);
+        next.cursor = Some(next_cursor(&first));
+        let next = next.validated().unwrap();
+        assert_eq!(next.sort.as_ref().unwrap().seed, seed);
+        assert_eq!(next.decoded_cursor().unwrap().unwrap().id, 12);
+    }
+
+    #[test]
+    fn rejects_a_cursor_of_another_seed() {
+        let first = random_sort(Some(1)).validated().unwrap();
+        let mut next = random_sort(Some(2));
+        next.cursor = Some(next_cursor(&first));
+        assert_eq!(next.decoded_cursor(), Err(CursorError::SortMismatch));
+    }
+
+    #[test]
+    fn rejects_a_cursor_of_other_filters() {
+        let first = random_sort(Some(1)).validated().unwrap();
+        let mut next = random_sort(Some(1));
+        next.cursor = Some(next_cursor(&first));
+        next.page = Some(4);
+        assert!(
+            next.decoded_cursor().is_ok(),
+            "paging is not part of the search"
+        );
+
+        next.beatmap = Some(Default::default());
+        next.beatmap.as_mut().unwrap().artist = Some("camellia".to_string());
+        assert_eq!(next.decoded_cursor(), Err(CursorError::FiltersMismatch));
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        let mut filters = Filters {
+            cursor: Some("not a cursor".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            filters.decoded_cursor(),
+            Err(CursorError::Malformed(_))
+        ));
+        filters.cursor = None;
+        assert_eq!(filters.decoded_cursor(), Ok(None));
+    }
+}
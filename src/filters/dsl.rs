@@ -0,0 +1,930 @@
+//! Search-box syntax compiled into [`Filters`].
+//!
+//! ```text
+//! stream>=20 bpm:180..220 status:ranked artist:"camellia" free text
+//! ```
+//!
+//! * `key>=value` / `key<=value` set one bound (`>` and `<` are accepted and
+//!   treated as inclusive, like every range in `Filters`)
+//! * `key:value` (or `key=value`) sets an exact value, `key:min..max` a range
+//!   where either bound may be omitted
+//! * values containing spaces are quoted, `\"` and `\\` escape inside quotes
+//! * anything that is not a `key<op>value` token is free text and ends up in
+//!   `BeatmapFilter.search_term`; quote free text that looks like `key:value`,
+//!   quoted text with spaces is searched as a phrase, and `-word` or
+//!   `-"some phrase"` excludes it
+//! * unknown keys are errors, so typos such as `stram>=20` are reported
+//!
+//! Every skillset name (`stream`, `jumpstream`, ...) is a key and adds one
+//! skillset constraint; `skillsets:or` switches the combinator.
+//!
+//! Beatmapset metadata: `video:yes`, `storyboard:no`, `explicit:no`,
+//! `featured:yes`, `source:touhou` (contains), `source_is:touhou` (exact),
+//! `tag:vocaloid` (exact, can be repeated), `tag_has:voca` (contains),
+//! `changed>=2024-01-01` / `changed:2024-01-01..2024-06-30` on the status
+//! change date (a bare end date includes that day) and `age<=30` for "changed
+//! in the last 30 days".
+//!
+//! Ordering: `sort:bpm order:desc`, `sort:relevance` for the best free-text
+//! matches first, and `sort:random seed:42` for a reproducible shuffle.
+//! `diffs:all`, `diffs:matched`, `diffs:easiest:3` or `diffs:closest:4:25`
+//! picks which difficulties of each set are shown.
+//!
+//! A leading `-` excludes: `-creator:name`, `-tag:gimmick`, `-pattern:jackspeed`
+//! and `-set:123456` can each be repeated.
+
+use super::{
+    end_of_day, format_datetime, format_datetime_end, parse_datetime, parse_datetime_end,
+    CentirateSelection, Filters, SkillsetFilter,
+};
+use crate::enums::Skillset;
+use std::fmt;
+use std::str::FromStr;
+
+/// Parse error with the character offset of the offending token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DslError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl DslError {
+    fn new(position: usize, message: impl Into<String>) -> Self {
+        Self {
+            position,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for DslError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "at position {}: {}", self.position, self.message)
+    }
+}
+
+impl std::error::Error for DslError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    /// `>=` or `>`
+    Ge,
+    /// `<=` or `<`
+    Le,
+    /// `:` or `=`
+    Eq,
+}
+
+#[derive(Debug)]
+enum TokenKind {
+    Text(String),
+    Field { key: String, op: Op, value: String },
+}
+
+#[derive(Debug)]
+struct Token {
+    position: usize,
+    negated: bool,
+    kind: TokenKind,
+}
+
+/// Read a quoted string starting at `start` (which must be `"`), returning the
+/// unescaped content and the index right after the closing quote.
+fn read_quoted(chars: &[char], start: usize) -> Result<(String, usize), DslError> {
+    let mut out = String::new();
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                out.push(chars[i + 1]);
+                i += 2;
+            }
+            '"' => return Ok((out, i + 1)),
+            c => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    Err(DslError::new(start, "unterminated quote"))
+}
+
+fn read_word(chars: &[char], start: usize) -> (String, usize) {
+    let mut i = start;
+    while i < chars.len() && !chars[i].is_whitespace() {
+        i += 1;
+    }
+    (chars[start..i].iter().collect(), i)
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, DslError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        if chars[i].is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let position = i;
+        let negated = chars[i] == '-' && chars.get(i + 1).is_some_and(|c| !c.is_whitespace());
+        if negated {
+            i += 1;
+        }
+
+        if chars[i] == '"' {
+            let (text, next) = read_quoted(&chars, i)?;
+            tokens.push(Token {
+                position,
+                negated,
+                kind: TokenKind::Text(text),
+            });
+            i = next;
+            continue;
+        }
+
+        let key_start = i;
+        while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+            i += 1;
+        }
+        let key: String = chars[key_start..i].iter().collect();
+        let op = match (chars.get(i), chars.get(i + 1)) {
+            (Some('>'), Some('=')) => Some((Op::Ge, 2)),
+            (Some('<'), Some('=')) => Some((Op::Le, 2)),
+            (Some('>'), _) => Some((Op::Ge, 1)),
+            (Some('<'), _) => Some((Op::Le, 1)),
+            (Some(':'), _) | (Some('='), _) => Some((Op::Eq, 1)),
+            _ => None,
+        };
+
+        match op {
+            Some((op, len)) if !key.is_empty() => {
+                i += len;
+                let value_start = i;
+                let value = if chars.get(i) == Some(&'"') {
+                    let (value, next) = read_quoted(&chars, i)?;
+                    i = next;
+                    value
+                } else {
+                    let (value, next) = read_word(&chars, i);
+                    i = next;
+                    if value.is_empty() {
+                        return Err(DslError::new(
+                            value_start,
+                            format!("missing value for `{}`", key),
+                        ));
+                    }
+                    value
+                };
+                tokens.push(Token {
+                    position,
+                    negated,
+                    kind: TokenKind::Field { key, op, value },
+                });
+            }
+            _ => {
+                let (word, next) = read_word(&chars, key_start);
+                i = next;
+                tokens.push(Token {
+                    position,
+                    negated,
+                    kind: TokenKind::Text(word),
+                });
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn parse_value<T: FromStr>(key: &str, value: &str) -> Result<T, String>
+where
+    T::Err: fmt::Display,
+{
+    value
+        .trim()
+        .parse()
+        .map_err(|e| format!("invalid value `{}` for `{}`: {}", value, key, e))
+}
+
+/// Apply `op value` to a min/max pair. `:` accepts `v`, `a..b`, `a..` and `..b`.
+fn set_bounds<T: FromStr + Copy>(
+    key: &str,
+    min: &mut Option<T>,
+    max: &mut Option<T>,
+    op: Op,
+    value: &str,
+) -> Result<(), String>
+where
+    T::Err: fmt::Display,
+{
+    match op {
+        Op::Ge => *min = Some(parse_value(key, value)?),
+        Op::Le => *max = Some(parse_value(key, value)?),
+        Op::Eq => match value.split_once("..") {
+            Some((lo, hi)) => {
+                if !lo.is_empty() {
+                    *min = Some(parse_value(key, lo)?);
+                }
+                if !hi.is_empty() {
+                    *max = Some(parse_value(key, hi)?);
+                }
+            }
+            None => {
+                let v = parse_value(key, value)?;
+                *min = Some(v);
+                *max = Some(v);
+            }
+        },
+    }
+    Ok(())
+}
+
+fn parse_bool(key: &str, op: Op, value: &str) -> Result<bool, String> {
+    if op != Op::Eq {
+        return Err(format!("`{}` only supports `:`", key));
+    }
+    match value.to_ascii_lowercase().as_str() {
+        "yes" | "true" | "1" => Ok(true),
+        "no" | "false" | "0" => Ok(false),
+        _ => Err(format!(
+            "invalid value `{}` for `{}`, expected yes or no",
+            value, key
+        )),
+    }
+}
+
+/// Parse a value for a key that only supports `:`.
+fn exact<T: FromStr>(key: &str, op: Op, value: &str) -> Result<T, String>
+where
+    T::Err: fmt::Display,
+{
+    if op != Op::Eq {
+        return Err(format!("`{}` only supports `:`", key));
+    }
+    parse_value(key, value)
+}
+
+fn apply_field(
+    filters: &mut Filters,
+    key: &str,
+    op: Op,
+    value: &str,
+    negated: bool,
+) -> Result<(), String> {
+    let key = key.to_ascii_lowercase();
+    let key = key.as_str();
+    if negated {
+        return apply_exclusion(filters, key, op, value);
+    }
+
+    if let Ok(skillset) = key.parse::<Skillset>() {
+        let mut constraint = SkillsetFilter {
+            pattern_type: Some(skillset),
+            ..Default::default()
+        };
+        set_bounds(
+            key,
+            &mut constraint.pattern_min,
+            &mut constraint.pattern_max,
+            op,
+            value,
+        )?;
+        filters
+            .skillsets
+            .get_or_insert_with(Default::default)
+            .constraints
+            .push(constraint);
+        return Ok(());
+    }
+
+    match key {
+        "skillsets" => {
+            filters
+                .skillsets
+                .get_or_insert_with(Default::default)
+                .combinator = Some(exact(key, op, value)?);
+        }
+        "type" => {
+            filters
+                .rating
+                .get_or_insert_with(Default::default)
+                .rating_type = Some(exact(key, op, value)?);
+        }
+        "rating" | "sr" | "stars" => {
+            let rating = filters.rating.get_or_insert_with(Default::default);
+            set_bounds(
+                key,
+                &mut rating.rating_min,
+                &mut rating.rating_max,
+                op,
+                value,
+            )?;
+        }
+        "artist" | "title" | "creator" | "mapper" => {
+            let beatmap = filters.beatmap.get_or_insert_with(Default::default);
+            let value = Some(exact::<String>(key, op, value)?);
+            match key {
+                "artist" => beatmap.artist = value,
+                "title" => beatmap.title = value,
+                _ => beatmap.creator = value,
+            }
+        }
+        "bpm" => {
+            let beatmap = filters.beatmap.get_or_insert_with(Default::default);
+            set_bounds(key, &mut beatmap.bpm_min, &mut beatmap.bpm_max, op, value)?;
+        }
+        "length" => {
+            let beatmap = filters.beatmap.get_or_insert_with(Default::default);
+            set_bounds(
+                key,
+                &mut beatmap.total_time_min,
+                &mut beatmap.total_time_max,
+                op,
+                value,
+            )?;
+        }
+        "status" => {
+            filters
+                .beatmap_technical
+                .get_or_insert_with(Default::default)
+                .status = Some(exact(key, op, value)?);
+        }
+        "mode" => {
+            filters
+                .beatmap_technical
+                .get_or_insert_with(Default::default)
+                .mode = Some(exact(key, op, value)?);
+        }
+        "od" | "cs" | "ar" | "hp" => {
+            let bt = filters
+                .beatmap_technical
+                .get_or_insert_with(Default::default);
+            let (min, max) = match key {
+                "od" => (&mut bt.od_min, &mut bt.od_max),
+                "cs" => (&mut bt.cs_min, &mut bt.cs_max),
+                "ar" => (&mut bt.ar_min, &mut bt.ar_max),
+                _ => (&mut bt.hp_min, &mut bt.hp_max),
+            };
+            set_bounds(key, min, max, op, value)?;
+        }
+        "keys" | "combo" | "circles" | "sliders" => {
+            let bt = filters
+                .beatmap_technical
+                .get_or_insert_with(Default::default);
+            let (min, max) = match key {
+                "keys" => (&mut bt.keycount_min, &mut bt.keycount_max),
+                "combo" => (&mut bt.max_combo_min, &mut bt.max_combo_max),
+                "circles" => (&mut bt.circles_min, &mut bt.circles_max),
+                _ => (&mut bt.sliders_min, &mut bt.sliders_max),
+            };
+            set_bounds(key, min, max, op, value)?;
+        }
+        "rate" => {
+            let rates = filters.rates.get_or_insert_with(Default::default);
+            rates.centirate = Some(match op {
+                Op::Eq => parse_value(key, value)?,
+                Op::Ge => CentirateSelection::Range {
+                    min: Some(parse_value(key, value)?),
+                    max: None,
+                },
+                Op::Le => CentirateSelection::Range {
+                    min: None,
+                    max: Some(parse_value(key, value)?),
+                },
+            });
+        }
+        "drain" => {
+            let rates = filters.rates.get_or_insert_with(Default::default);
+            set_bounds(
+                key,
+                &mut rates.drain_time_min,
+                &mut rates.drain_time_max,
+                op,
+                value,
+            )?;
+        }
+        "video" | "storyboard" | "explicit" | "featured" => {
+            let bs = filters.beatmapset.get_or_insert_with(Default::default);
+            let value = Some(parse_bool(key, op, value)?);
+            match key {
+                "video" => bs.has_video = value,
+                "storyboard" => bs.has_storyboard = value,
+                "explicit" => bs.is_explicit = value,
+                _ => bs.is_featured = value,
+            }
+        }
+        "source" => {
+            filters
+                .beatmapset
+                .get_or_insert_with(Default::default)
+                .source_contains = Some(exact(key, op, value)?);
+        }
+        "source_is" => {
+            filters
+                .beatmapset
+                .get_or_insert_with(Default::default)
+                .source = Some(exact(key, op, value)?);
+        }
+        "tag" => {
+            filters
+                .beatmapset
+                .get_or_insert_with(Default::default)
+                .tags
+                .push(exact(key, op, value)?);
+        }
+        "tag_has" => {
+            filters
+                .beatmapset
+                .get_or_insert_with(Default::default)
+                .tag_contains = Some(exact(key, op, value)?);
+        }
+        "changed" => {
+            let bs = filters.beatmapset.get_or_insert_with(Default::default);
+            match op {
+                Op::Ge => bs.status_changed_from = Some(parse_datetime(value)?),
+                Op::Le => bs.status_changed_to = Some(parse_datetime_end(value)?),
+                Op::Eq => match value.split_once("..") {
+                    Some((from, to)) => {
+                        if !from.is_empty() {
+                            bs.status_changed_from = Some(parse_datetime(from)?);
+                        }
+                        if !to.is_empty() {
+                            bs.status_changed_to = Some(parse_datetime_end(to)?);
+                        }
+                    }
+                    // A single date covers that whole day
+                    None => {
+                        let day = parse_datetime(value)?;
+                        bs.status_changed_from = Some(day);
+                        bs.status_changed_to = Some(end_of_day(day.date()));
+                    }
+                },
+            }
+        }
+        "age" => {
+            if op == Op::Ge {
+                return Err("`age` only supports `<=` or `:`".to_string());
+            }
+            filters
+                .beatmapset
+                .get_or_insert_with(Default::default)
+                .status_changed_within_days = Some(parse_value(key, value)?);
+        }
+        "sort" => {
+            filters.sort.get_or_insert_with(Default::default).key = Some(exact(key, op, value)?)
+        }
+        "order" => {
+            filters.sort.get_or_insert_with(Default::default).order = Some(exact(key, op, value)?)
+        }
+        "seed" => {
+            filters.sort.get_or_insert_with(Default::default).seed = Some(exact(key, op, value)?)
+        }
+        "diffs" => filters.truncation = Some(exact(key, op, value)?),
+        "page" => filters.page = Some(exact(key, op, value)?),
+        "per_page" => filters.per_page = Some(exact(key, op, value)?),
+        _ => return Err(format!("unknown field `{}`", key)),
+    }
+    Ok(())
+}
+
+fn apply_exclusion(filters: &mut Filters, key: &str, op: Op, value: &str) -> Result<(), String> {
+    let exclude = filters.exclude.get_or_insert_with(Default::default);
+    match key {
+        "creator" | "mapper" => exclude.creators.push(exact(key, op, value)?),
+        "tag" => exclude.tags.push(exact(key, op, value)?),
+        "pattern" => exclude.patterns.push(exact(key, op, value)?),
+        "set" => exclude.beatmapset_ids.push(exact(key, op, value)?),
+        _ => return Err(format!("`-{}` is not supported", key)),
+    }
+    Ok(())
+}
+
+/// Parse a search string into `Filters`.
+pub fn parse(input: &str) -> Result<Filters, DslError> {
+    let mut filters = Filters::default();
+    let mut text: Vec<String> = Vec::new();
+
+    for token in tokenize(input)? {
+        match token.kind {
+            TokenKind::Text(word) => {
+                // `-` excludes the word or phrase from the full-text search
+                let sign = if token.negated { "-" } else { "" };
+                if word.contains(char::is_whitespace) {
+                    // Keep the phrase together for the full-text search
+                    text.push(format!("{}\"{}\"", sign, word));
+                } else {
+                    text.push(format!("{}{}", sign, word));
+                }
+            }
+            TokenKind::Field { key, op, value } => {
+                apply_field(&mut filters, &key, op, &value, token.negated)
+                    .map_err(|message| DslError::new(token.position, message))?;
+            }
+        }
+    }
+
+    if !text.is_empty() {
+        filters
+            .beatmap
+            .get_or_insert_with(Default::default)
+            .search_term = Some(text.join(" "));
+    }
+    Ok(filters)
+}
+
+/// Quote a value when it would not survive tokenization as a bare word.
+fn quote(value: &str) -> String {
+    let needs_quotes = value.is_empty()
+        || value.starts_with('-')
+        || value
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '\\' | ':' | '=' | '<' | '>'));
+    if !needs_quotes {
+        return value.to_string();
+    }
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        if c == '"' || c == '\\' {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('"');
+    out
+}
+
+/// Words and `"quoted phrases"` (without their quotes) of a search term,
+/// each with whether a leading `-` excludes it.
+fn search_term_parts(term: &str) -> Vec<(bool, String)> {
+    let mut parts = Vec::new();
+    let mut negate_phrase = false;
+    for (i, chunk) in term.split('"').enumerate() {
+        if i % 2 == 1 {
+            let phrase = chunk.trim();
+            if !phrase.is_empty() {
+                parts.push((negate_phrase, phrase.to_string()));
+            }
+            continue;
+        }
+        let mut words: Vec<&str> = chunk.split_whitespace().collect();
+        // `-"phrase"`: the sign is the last word before the quote
+        negate_phrase = chunk.ends_with('-') && words.last() == Some(&"-");
+        if negate_phrase {
+            words.pop();
+        }
+        for word in words {
+            match word.strip_prefix('-').filter(|rest| !rest.is_empty()) {
+                Some(rest) => parts.push((true, rest.to_string())),
+                None => parts.push((false, word.to_string())),
+            }
+        }
+    }
+    parts
+}
+
+fn push_bounds<T: fmt::Display + PartialEq>(
+    parts: &mut Vec<String>,
+    key: &str,
+    min: Option<&T>,
+    max: Option<&T>,
+) {
+    match (min, max) {
+        (Some(min), Some(max)) if min == max => parts.push(format!("{}:{}", key, min)),
+        (Some(min), Some(max)) => parts.push(format!("{}:{}..{}", key, min, max)),
+        (Some(min), None) => parts.push(format!("{}>={}", key, min)),
+        (None, Some(max)) => parts.push(format!("{}<={}", key, max)),
+        (None, None) => {}
+    }
+}
+
+/// Render `Filters` back into the search syntax accepted by [`parse`].
+pub fn to_dsl(filters: &Filters) -> String {
+    let mut parts: Vec<String> = Vec::new();
+
+    if let Some(beatmap) = filters.beatmap.as_ref() {
+        if let Some(term) = beatmap.search_term.as_ref() {
+            for (negated, part) in search_term_parts(term) {
+                let sign = if negated { "-" } else { "" };
+                parts.push(format!("{}{}", sign, quote(&part)));
+            }
+        }
+    }
+    if let Some(rating) = filters.rating.as_ref() {
+        if let Some(rt) = rating.rating_type {
+            parts.push(format!("type:{}", rt));
+        }
+        push_bounds(
+            &mut parts,
+            "rating",
+            rating.rating_min.as_ref(),
+            rating.rating_max.as_ref(),
+        );
+    }
+    if let Some(skillsets) = filters.skillsets.as_ref() {
+        for c in &skillsets.constraints {
+            if let Some(pt) = c.pattern_type {
+                match (c.pattern_min, c.pattern_max) {
+                    // A bare constraint only requires the pattern to be present
+                    (None, None) => parts.push(format!("{}:..", pt)),
+                    (min, max) => push_bounds(&mut parts, pt.as_str(), min.as_ref(), max.as_ref()),
+                }
+            }
+        }
+        if let Some(combinator) = skillsets.combinator {
+            parts.push(format!("skillsets:{}", combinator.as_str()));
+        }
+    }
+    if let Some(beatmap) = filters.beatmap.as_ref() {
+        if let Some(artist) = beatmap.artist.as_ref() {
+            parts.push(format!("artist:{}", quote(artist)));
+        }
+        if let Some(title) = beatmap.title.as_ref() {
+            parts.push(format!("title:{}", quote(title)));
+        }
+        if let Some(creator) = beatmap.creator.as_ref() {
+            parts.push(format!("creator:{}", quote(creator)));
+        }
+        push_bounds(
+            &mut parts,
+            "bpm",
+            beatmap.bpm_min.as_ref(),
+            beatmap.bpm_max.as_ref(),
+        );
+        push_bounds(
+            &mut parts,
+            "length",
+            beatmap.total_time_min.as_ref(),
+            beatmap.total_time_max.as_ref(),
+        );
+    }
+    if let Some(bt) = filters.beatmap_technical.as_ref() {
+        if let Some(status) = bt.status {
+            parts.push(format!("status:{}", status));
+        }
+        if let Some(mode) = bt.mode {
+            parts.push(format!("mode:{}", mode));
+        }
+        push_bounds(&mut parts, "od", bt.od_min.as_ref(), bt.od_max.as_ref());
+        push_bounds(&mut parts, "cs", bt.cs_min.as_ref(), bt.cs_max.as_ref());
+        push_bounds(&mut parts, "ar", bt.ar_min.as_ref(), bt.ar_max.as_ref());
+        push_bounds(&mut parts, "hp", bt.hp_min.as_ref(), bt.hp_max.as_ref());
+        push_bounds(
+            &mut parts,
+            "keys",
+            bt.keycount_min.as_ref(),
+            bt.keycount_max.as_ref(),
+        );
+        push_bounds(
+            &mut parts,
+            "combo",
+            bt.max_combo_min.as_ref(),
+            bt.max_combo_max.as_ref(),
+        );
+        push_bounds(
+            &mut parts,
+            "circles",
+            bt.circles_min.as_ref(),
+            bt.circles_max.as_ref(),
+        );
+        push_bounds(
+            &mut parts,
+            "sliders",
+            bt.sliders_min.as_ref(),
+            bt.sliders_max.as_ref(),
+        );
+    }
+    if let Some(rates) = filters.rates.as_ref() {
+        if let Some(centirate) = rates.centirate.as_ref() {
+            parts.push(format!("rate:{}", centirate));
+        }
+        push_bounds(
+            &mut parts,
+            "drain",
+            rates.drain_time_min.as_ref(),
+            rates.drain_time_max.as_ref(),
+        );
+    }
+    if let Some(bs) = filters.beatmapset.as_ref() {
+        let yes_no = |v: bool| if v { "yes" } else { "no" };
+        if let Some(v) = bs.has_video {
+            parts.push(format!("video:{}", yes_no(v)));
+        }
+        if let Some(v) = bs.has_storyboard {
+            parts.push(format!("storyboard:{}", yes_no(v)));
+        }
+        if let Some(v) = bs.is_explicit {
+            parts.push(format!("explicit:{}", yes_no(v)));
+        }
+        if let Some(v) = bs.is_featured {
+            parts.push(format!("featured:{}", yes_no(v)));
+        }
+        if let Some(source) = bs.source.as_ref() {
+            parts.push(format!("source_is:{}", quote(source)));
+        }
+        if let Some(source) = bs.source_contains.as_ref() {
+            parts.push(format!("source:{}", quote(source)));
+        }
+        for tag in &bs.tags {
+            parts.push(format!("tag:{}", quote(tag)));
+        }
+        if let Some(tag) = bs.tag_contains.as_ref() {
+            parts.push(format!("tag_has:{}", quote(tag)));
+        }
+        push_bounds(
+            &mut parts,
+            "changed",
+            bs.status_changed_from
+                .as_ref()
+                .map(format_datetime)
+                .as_ref(),
+            bs.status_changed_to
+                .as_ref()
+                .map(format_datetime_end)
+                .as_ref(),
+        );
+        if let Some(days) = bs.status_changed_within_days {
+            parts.push(format!("age<={}", days));
+        }
+    }
+    if let Some(exclude) = filters.exclude.as_ref() {
+        for creator in &exclude.creators {
+            parts.push(format!("-creator:{}", quote(creator)));
+        }
+        for tag in &exclude.tags {
+            parts.push(format!("-tag:{}", quote(tag)));
+        }
+        for pattern in &exclude.patterns {
+            parts.push(format!("-pattern:{}", pattern));
+        }
+        for id in &exclude.beatmapset_ids {
+            parts.push(format!("-set:{}", id));
+        }
+    }
+    if let Some(sort) = filters.sort.as_ref() {
+        if let Some(key) = sort.key {
+            parts.push(format!("sort:{}", key));
+        }
+        if let Some(order) = sort.order {
+            parts.push(format!("order:{}", order));
+        }
+        if let Some(seed) = sort.seed {
+            parts.push(format!("seed:{}", seed));
+        }
+    }
+    if let Some(truncation) = filters.truncation.as_ref() {
+        parts.push(format!("diffs:{}", truncation));
+    }
+    if let Some(page) = filters.page {
+        parts.push(format!("page:{}", page));
+    }
+    if let Some(per_page) = filters.per_page {
+        parts.push(format!("per_page:{}", per_page));
+    }
+
+    parts.join(" ")
+}
+
+impl Filters {
+    /// Parse the search-box syntax described in the module docs.
+    pub fn from_dsl(input: &str) -> Result<Self, DslError> {
+        parse(input)
+    }
+
+    /// Render these filters in the search-box syntax.
+    pub fn to_dsl(&self) -> String {
+        to_dsl(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enums::{GameMode, RankStatus, RatingType};
+    use crate::filters::{SkillsetCombinator, SortKey, SortOrder, TruncationPolicy};
+
+    /// Already in `to_dsl` order and spelling, so it must come back unchanged.
+    const CANONICAL: &str = concat!(
+        "camellia \"big black\" type:etterna rating:20.5..30 stream>=20 jackspeed<=15 ",
+        "skillsets:or creator:\"Some One\" bpm:180..220 status:ranked mode:mania keys:4 ",
+        "rate:100,110,150 video:no source_is:touhou source:\"touhou project\" tag:vocaloid ",
+        "tag_has:voca changed:2024-01-01..2024-01-31 -creator:nobody -tag:gimmick ",
+        "-pattern:chordjack -set:123 sort:bpm order:desc diffs:closest:4:25 per_page:20",
+    );
+
+    #[test]
+    fn parses_fields_and_free_text() {
+        let filters = parse(CANONICAL).unwrap();
+
+        let rating = filters.rating.as_ref().unwrap();
+        assert_eq!(rating.rating_type, Some(RatingType::Etterna));
+        assert_eq!(
+            (rating.rating_min, rating.rating_max),
+            (Some(20.5), Some(30.0))
+        );
+        let skillsets = filters.skillsets.as_ref().unwrap();
+        assert_eq!(skillsets.constraints.len(), 2);
+        assert_eq!(skillsets.combinator, Some(SkillsetCombinator::Or));
+        let beatmap = filters.beatmap.as_ref().unwrap();
+        assert_eq!(
+            beatmap.search_term.as_deref(),
+            Some("camellia \"big black\"")
+        );
+        assert_eq!(beatmap.creator.as_deref(), Some("Some One"));
+        let technical = filters.beatmap_technical.as_ref().unwrap();
+        assert_eq!(technical.status, Some(RankStatus::Ranked));
+        assert_eq!(technical.mode, Some(GameMode::Mania));
+        assert_eq!(
+            (technical.keycount_min, technical.keycount_max),
+            (Some(4), Some(4))
+        );
+        let set = filters.beatmapset.as_ref().unwrap();
+        assert_eq!(set.source.as_deref(), Some("touhou"));
+        assert_eq!(set.source_contains.as_deref(), Some("touhou project"));
+        assert_eq!(
+            set.status_changed_to.unwrap().to_string(),
+            "2024-01-31 23:59:59.999999"
+        );
+        let sort = filters.sort.as_ref().unwrap();
+        assert_eq!(sort.key, Some(SortKey::Bpm));
+        assert_eq!(sort.order, Some(SortOrder::Desc));
+        assert_eq!(
+            filters.truncation,
+            Some(TruncationPolicy::ClosestToRating { n: 4, target: 25.0 })
+        );
+        assert_eq!(filters.exclude.as_ref().unwrap().beatmapset_ids, vec![123]);
+    }
+
+    #[test]
+    fn round_trips_canonical_input() {
+        assert_eq!(parse(CANONICAL).unwrap().to_dsl(), CANONICAL);
+    }
+
+    #[test]
+    fn round_trips_through_query_string() {
+        let filters = parse(CANONICAL).unwrap();
+        let again = parse(&filters.to_dsl()).unwrap();
+        assert_eq!(again.to_query_string(), filters.to_query_string());
+    }
+
+    #[test]
+    fn normalizes_operators_and_quoting() {
+        let filters = parse("bpm>180 bpm<220 artist=\"say \\\"hi\\\"\" rate:110..").unwrap();
+        assert_eq!(
+            filters.to_dsl(),
+            "artist:\"say \\\"hi\\\"\" bpm:180..220 rate:110.."
+        );
+        assert_eq!(
+            parse(&filters.to_dsl())
+                .unwrap()
+                .beatmap
+                .unwrap()
+                .artist
+                .as_deref(),
+            Some("say \"hi\"")
+        );
+    }
+
+    #[test]
+    fn round_trips_negated_free_text() {
+        let input = "camellia -remix -\"big black\" -\"re:zero\" bpm>=180";
+        let filters = parse(input).unwrap();
+        assert_eq!(
+            filters.beatmap.as_ref().unwrap().search_term.as_deref(),
+            Some("camellia -remix -\"big black\" -re:zero")
+        );
+        assert_eq!(filters.to_dsl(), input);
+    }
+
+    #[test]
+    fn quotes_free_text_that_looks_like_a_field() {
+        let filters = parse("\"re:zero\"").unwrap();
+        assert_eq!(
+            filters.beatmap.as_ref().unwrap().search_term.as_deref(),
+            Some("re:zero")
+        );
+        assert_eq!(filters.to_dsl(), "\"re:zero\"");
+    }
+
+    #[test]
+    fn reports_error_positions() {
+        let cases = [
+            ("bpm>=180 stram>=20", 9),
+            ("stream>=20 artist:\"camellia", 18),
+            ("bpm>=", 5),
+            ("keys:4 -bpm:180", 7),
+        ];
+        for (input, position) in cases {
+            let err = parse(input).unwrap_err();
+            assert_eq!(err.position, position, "{}: {}", input, err);
+        }
+    }
+
+    #[test]
+    fn names_the_unknown_key() {
+        let err = parse("stram>=20").unwrap_err();
+        assert_eq!(err.message, "unknown field `stram`");
+    }
+}
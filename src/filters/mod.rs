@@ -0,0 +1,559 @@
+pub mod builder;
+pub mod cursor;
+pub mod dsl;
+pub mod query_string;
+pub mod token;
+pub mod validate;
+
+use crate::enums::{GameMode, RankStatus, RatingType, Skillset};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+pub use builder::FiltersBuilder;
+pub use cursor::{CursorError, SearchCursor};
+pub use dsl::DslError;
+pub use query_string::QueryStringError;
+pub use token::TokenError;
+pub use validate::{FilterErrorKind, FilterFieldError, ValidFilters};
+
+#[derive(Deserialize, Debug, Clone, Default, ToSchema)]
+pub struct RatingFilter {
+    pub rating_type: Option<RatingType>,
+    pub rating_min: Option<f64>,
+    pub rating_max: Option<f64>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default, ToSchema)]
+pub struct SkillsetFilter {
+    pub pattern_type: Option<Skillset>,
+    pub pattern_min: Option<f64>,
+    pub pattern_max: Option<f64>,
+}
+
+/// How the constraints of a `SkillsetsFilter` are combined
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SkillsetCombinator {
+    #[default]
+    And,
+    Or,
+}
+
+impl SkillsetCombinator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SkillsetCombinator::And => "and",
+            SkillsetCombinator::Or => "or",
+        }
+    }
+}
+
+impl FromStr for SkillsetCombinator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "and" => Ok(SkillsetCombinator::And),
+            "or" => Ok(SkillsetCombinator::Or),
+            _ => Err(format!(
+                "invalid combinator `{}`, expected `and` or `or`",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default, ToSchema)]
+pub struct SkillsetsFilter {
+    /// Defaults to `and` (every constraint must match)
+    pub combinator: Option<SkillsetCombinator>,
+    pub constraints: Vec<SkillsetFilter>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default, ToSchema)]
+pub struct BeatmapFilter {
+    /// Free text searched in titles, artists, creator, source, tags and
+    /// difficulty names; `"quoted phrases"`, `or` and `-word` are supported
+    pub search_term: Option<String>,
+    /// Case-insensitive substring matches on a single field
+    pub artist: Option<String>,
+    pub title: Option<String>,
+    pub creator: Option<String>,
+    pub total_time_min: Option<i32>,
+    pub total_time_max: Option<i32>,
+    pub bpm_min: Option<f64>,
+    pub bpm_max: Option<f64>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default, ToSchema)]
+pub struct BeatmapTechnicalFilter {
+    /// Overall Difficulty (OD) range
+    pub od_min: Option<f64>,
+    pub od_max: Option<f64>,
+    /// Beatmap status (pending, ranked, qualified, loved, graveyard, ...)
+    pub status: Option<RankStatus>,
+    /// Game mode (osu, taiko, fruits, mania)
+    pub mode: Option<GameMode>,
+    /// Mania key count, stored as CS
+    pub keycount_min: Option<i32>,
+    pub keycount_max: Option<i32>,
+    /// Circle Size (CS) range, 0 to 10; use the key count for mania
+    pub cs_min: Option<f64>,
+    pub cs_max: Option<f64>,
+    /// Approach Rate (AR) range
+    pub ar_min: Option<f64>,
+    pub ar_max: Option<f64>,
+    /// HP drain range
+    pub hp_min: Option<f64>,
+    pub hp_max: Option<f64>,
+    /// Max combo range
+    pub max_combo_min: Option<i32>,
+    pub max_combo_max: Option<i32>,
+    /// Circle (note) count range
+    pub circles_min: Option<i32>,
+    pub circles_max: Option<i32>,
+    /// Slider (long note) count range
+    pub sliders_min: Option<i32>,
+    pub sliders_max: Option<i32>,
+}
+
+/// Which stored rates a search looks at
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CentirateSelection {
+    /// Every stored rate
+    Any,
+    /// A single centirate (100 = 1.0x)
+    Exact(i32),
+    /// Inclusive centirate range, a missing bound is open
+    Range { min: Option<i32>, max: Option<i32> },
+    /// Any of these centirates
+    List(Vec<i32>),
+}
+
+impl Default for CentirateSelection {
+    fn default() -> Self {
+        CentirateSelection::Exact(100)
+    }
+}
+
+/// Text form used in query strings: `any`, `110`, `110..150`, `110..`, `..150`
+/// or `100,110,150`.
+impl fmt::Display for CentirateSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CentirateSelection::Any => f.write_str("any"),
+            CentirateSelection::Exact(centirate) => write!(f, "{}", centirate),
+            CentirateSelection::Range { min, max } => {
+                if let Some(min) = min {
+                    write!(f, "{}", min)?;
+                }
+                f.write_str("..")?;
+                if let Some(max) = max {
+                    write!(f, "{}", max)?;
+                }
+                Ok(())
+            }
+            CentirateSelection::List(centirates) => {
+                let values: Vec<String> = centirates.iter().map(i32::to_string).collect();
+                f.write_str(&values.join(","))
+            }
+        }
+    }
+}
+
+impl FromStr for CentirateSelection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |v: &str| {
+            v.trim()
+                .parse::<i32>()
+                .map_err(|_| format!("invalid centirate `{}`", v))
+        };
+        if s.eq_ignore_ascii_case("any") {
+            return Ok(CentirateSelection::Any);
+        }
+        if s.contains(',') {
+            return s
+                .split(',')
+                .filter(|v| !v.trim().is_empty())
+                .map(parse)
+                .collect::<Result<_, _>>()
+                .map(CentirateSelection::List);
+        }
+        match s.split_once("..") {
+            Some((min, max)) => Ok(CentirateSelection::Range {
+                min: (!min.is_empty()).then(|| parse(min)).transpose()?,
+                max: (!max.is_empty()).then(|| parse(max)).transpose()?,
+            }),
+            None => Ok(CentirateSelection::Exact(parse(s)?)),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default, ToSchema)]
+pub struct RatesFilter {
+    /// Rates to search, defaults to 1.0x only.
+    /// Rating, BPM and length filters apply to the selected rates.
+    pub centirate: Option<CentirateSelection>,
+    /// Drain time in seconds
+    pub drain_time_min: Option<i32>,
+    pub drain_time_max: Option<i32>,
+}
+
+/// Beatmapset metadata filters
+#[derive(Deserialize, Debug, Clone, Default, ToSchema)]
+pub struct BeatmapsetFilter {
+    pub has_video: Option<bool>,
+    pub has_storyboard: Option<bool>,
+    pub is_explicit: Option<bool>,
+    pub is_featured: Option<bool>,
+    /// Exact source, case-insensitive
+    pub source: Option<String>,
+    /// Source contains, case-insensitive
+    pub source_contains: Option<String>,
+    /// Every tag must be present (exact match)
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// At least one tag contains this text, case-insensitive
+    pub tag_contains: Option<String>,
+    /// Range on `osu_status_changed_at`
+    pub status_changed_from: Option<NaiveDateTime>,
+    pub status_changed_to: Option<NaiveDateTime>,
+    /// Status changed within the last N days ("ranked in the last 30 days")
+    pub status_changed_within_days: Option<i32>,
+}
+
+/// Parse `YYYY-MM-DD` (midnight) or `YYYY-MM-DDTHH:MM:SS[.f]`.
+pub(crate) fn parse_datetime(s: &str) -> Result<NaiveDateTime, String> {
+    let s = s.trim();
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f")
+        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f"))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .map(|d| d.and_hms_opt(0, 0, 0).unwrap_or_default())
+        })
+        .map_err(|_| format!("invalid date `{}`, expected YYYY-MM-DD[THH:MM:SS]", s))
+}
+
+/// Inverse of [`parse_datetime`], omitting the time at midnight.
+pub(crate) fn format_datetime(dt: &NaiveDateTime) -> String {
+    if dt.time() == chrono::NaiveTime::MIN {
+        dt.format("%Y-%m-%d").to_string()
+    } else {
+        dt.format("%Y-%m-%dT%H:%M:%S%.f").to_string()
+    }
+}
+
+/// Last instant of a day, timestamps are stored to the microsecond.
+pub(crate) fn end_of_day(date: NaiveDate) -> NaiveDateTime {
+    date.and_hms_micro_opt(23, 59, 59, 999_999)
+        .unwrap_or_default()
+}
+
+/// [`parse_datetime`] for an upper bound: a bare date includes that whole day.
+pub(crate) fn parse_datetime_end(s: &str) -> Result<NaiveDateTime, String> {
+    match NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d") {
+        Ok(date) => Ok(end_of_day(date)),
+        Err(_) => parse_datetime(s),
+    }
+}
+
+/// Inverse of [`parse_datetime_end`], omitting the time at the end of a day.
+pub(crate) fn format_datetime_end(dt: &NaiveDateTime) -> String {
+    if *dt == end_of_day(dt.date()) {
+        dt.format("%Y-%m-%d").to_string()
+    } else {
+        format_datetime(dt)
+    }
+}
+
+/// Negative constraints: anything matching one of these lists is hidden.
+#[derive(Deserialize, Debug, Clone, Default, ToSchema)]
+#[serde(default)]
+pub struct ExclusionFilter {
+    /// Creator names, compared case-insensitively
+    pub creators: Vec<String>,
+    /// Beatmapsets carrying any of these tags are hidden
+    pub tags: Vec<String>,
+    /// Beatmaps whose `main_pattern` contains any of these are hidden
+    pub patterns: Vec<Skillset>,
+    /// osu! beatmapset ids
+    pub beatmapset_ids: Vec<i32>,
+}
+
+/// What beatmapsets are ordered by
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    /// Internal beatmapset id, the historical order
+    #[default]
+    Id,
+    /// Rating of the preferred rating type (easiest matching difficulty when
+    /// ascending, hardest when descending)
+    Rating,
+    Bpm,
+    /// Total length
+    Length,
+    Od,
+    /// `osu_status_changed_at`
+    StatusChangedAt,
+    Artist,
+    Title,
+    /// Shuffled, stable for a given seed
+    Random,
+    /// How well the set matches `beatmap.search_term`, best first by default
+    Relevance,
+}
+
+impl SortKey {
+    pub const ALL: &'static [SortKey] = &[
+        SortKey::Id,
+        SortKey::Rating,
+        SortKey::Bpm,
+        SortKey::Length,
+        SortKey::Od,
+        SortKey::StatusChangedAt,
+        SortKey::Artist,
+        SortKey::Title,
+        SortKey::Random,
+        SortKey::Relevance,
+    ];
+    pub const VALUES: &'static [&'static str] = &[
+        "id",
+        "rating",
+        "bpm",
+        "length",
+        "od",
+        "status_changed_at",
+        "artist",
+        "title",
+        "random",
+        "relevance",
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortKey::Id => "id",
+            SortKey::Rating => "rating",
+            SortKey::Bpm => "bpm",
+            SortKey::Length => "length",
+            SortKey::Od => "od",
+            SortKey::StatusChangedAt => "status_changed_at",
+            SortKey::Artist => "artist",
+            SortKey::Title => "title",
+            SortKey::Random => "random",
+            SortKey::Relevance => "relevance",
+        }
+    }
+
+    /// Order used when none is given: newest first for dates, most relevant
+    /// first, ascending otherwise.
+    pub fn default_order(&self) -> SortOrder {
+        match self {
+            SortKey::StatusChangedAt | SortKey::Relevance => SortOrder::Desc,
+            _ => SortOrder::Asc,
+        }
+    }
+}
+
+impl fmt::Display for SortKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let key = s.to_ascii_lowercase();
+        Self::VALUES
+            .iter()
+            .position(|v| *v == key)
+            .map(|i| SortKey::ALL[i])
+            .ok_or_else(|| {
+                format!(
+                    "invalid sort key `{}`, expected one of: {}",
+                    s,
+                    Self::VALUES.join(", ")
+                )
+            })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    pub const VALUES: &'static [&'static str] = &["asc", "desc"];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => "asc",
+            SortOrder::Desc => "desc",
+        }
+    }
+}
+
+impl fmt::Display for SortOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "asc" => Ok(SortOrder::Asc),
+            "desc" => Ok(SortOrder::Desc),
+            _ => Err(format!(
+                "invalid sort order `{}`, expected `asc` or `desc`",
+                s
+            )),
+        }
+    }
+}
+
+/// Seed of a `random` sort that was never normalized.
+pub const DEFAULT_RANDOM_SEED: i64 = 0;
+
+#[derive(Deserialize, Debug, Clone, Default, ToSchema)]
+pub struct SortOptions {
+    /// Defaults to `id`
+    pub key: Option<SortKey>,
+    /// Defaults to the key's natural order (see `SortKey::default_order`)
+    pub order: Option<SortOrder>,
+    /// Seed for `random`; the same seed gives the same order on every page.
+    /// Drawn by `Filters::normalize` when missing.
+    pub seed: Option<i64>,
+}
+
+impl SortOptions {
+    pub fn key(&self) -> SortKey {
+        self.key.unwrap_or_default()
+    }
+
+    pub fn order(&self) -> SortOrder {
+        self.order.unwrap_or_else(|| self.key().default_order())
+    }
+
+    /// Defaults to `DEFAULT_RANDOM_SEED` for filters that skipped
+    /// `Filters::normalize`.
+    pub fn seed(&self) -> i64 {
+        self.seed.unwrap_or(DEFAULT_RANDOM_SEED)
+    }
+}
+
+/// Which difficulties of each beatmapset a short result keeps
+#[derive(Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TruncationPolicy {
+    /// Every difficulty of the set, each flagged `matched` or not
+    All,
+    /// The `n` easiest plus the hardest
+    EasiestPlusHardest { n: usize },
+    /// The `n` closest to `target` in the preferred rating type
+    ClosestToRating { n: usize, target: f64 },
+    /// Every difficulty that matched the filters
+    MatchedOnly,
+}
+
+impl Default for TruncationPolicy {
+    fn default() -> Self {
+        TruncationPolicy::EasiestPlusHardest { n: 5 }
+    }
+}
+
+/// Text form used in query strings: `all`, `matched`, `easiest:5` or `closest:5:4.5`.
+impl fmt::Display for TruncationPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TruncationPolicy::All => f.write_str("all"),
+            TruncationPolicy::EasiestPlusHardest { n } => write!(f, "easiest:{}", n),
+            TruncationPolicy::ClosestToRating { n, target } => {
+                write!(f, "closest:{}:{}", n, target)
+            }
+            TruncationPolicy::MatchedOnly => f.write_str("matched"),
+        }
+    }
+}
+
+impl FromStr for TruncationPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid truncation `{}`, expected `all`, `matched`, `easiest:<n>` or `closest:<n>:<rating>`",
+                s
+            )
+        };
+        let mut parts = s.split(':');
+        let policy = match parts.next().map(str::to_ascii_lowercase).as_deref() {
+            Some("all") => TruncationPolicy::All,
+            Some("matched") => TruncationPolicy::MatchedOnly,
+            Some("easiest") => TruncationPolicy::EasiestPlusHardest {
+                n: parts
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or_else(invalid)?,
+            },
+            Some("closest") => TruncationPolicy::ClosestToRating {
+                n: parts
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or_else(invalid)?,
+                target: parts
+                    .next()
+                    .and_then(|t| t.parse().ok())
+                    .ok_or_else(invalid)?,
+            },
+            _ => return Err(invalid()),
+        };
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+        Ok(policy)
+    }
+}
+
+/// Search filters shared by the short beatmapset queries.
+///
+/// Deserializes from nested JSON; see [`query_string`] for the flat
+/// `rating.type=osu&rating.min=3` encoding used in URLs.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct Filters {
+    pub rating: Option<RatingFilter>,
+    pub skillsets: Option<SkillsetsFilter>,
+    pub beatmap: Option<BeatmapFilter>,
+    pub beatmap_technical: Option<BeatmapTechnicalFilter>,
+    pub rates: Option<RatesFilter>,
+    pub beatmapset: Option<BeatmapsetFilter>,
+    pub exclude: Option<ExclusionFilter>,
+    pub sort: Option<SortOptions>,
+    /// Difficulties kept per beatmapset, defaults to the 5 easiest plus the hardest
+    pub truncation: Option<TruncationPolicy>,
+    /// Keyset cursor (`next_cursor` / `prev_cursor` of a previous page),
+    /// takes precedence over `page`
+    pub cursor: Option<String>,
+    pub page: Option<usize>,
+    pub per_page: Option<usize>,
+}
+
+impl Filters {
+    pub fn builder() -> FiltersBuilder {
+        FiltersBuilder::default()
+    }
+}
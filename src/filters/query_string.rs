@@ -0,0 +1,760 @@
+//! Flat query-string encoding of [`Filters`].
+//!
+//! Every leaf of the nested filter structs maps to one dotted key, e.g.
+//! `rating.type=osu&rating.min=3&skillset.0.type=stream&skillset.0.min=20`.
+//! Skillset constraints are indexed (`skillset.{n}.*`) so several can be sent
+//! at once; all other keys appear at most once.
+
+use super::{
+    format_datetime, format_datetime_end, parse_datetime, parse_datetime_end, Filters,
+    SkillsetFilter, SortKey, SortOrder,
+};
+use crate::enums::{GameMode, RankStatus, RatingType, Skillset};
+use std::fmt;
+use utoipa::openapi::path::{Parameter, ParameterBuilder, ParameterIn, ParameterStyle};
+use utoipa::openapi::schema::{ArrayBuilder, ObjectBuilder, Schema, Type};
+use utoipa::openapi::{RefOr, Required};
+use utoipa::IntoParams;
+
+/// Error raised when a query string cannot be decoded into `Filters`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryStringError {
+    pub key: String,
+    pub message: String,
+}
+
+impl QueryStringError {
+    fn new(key: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for QueryStringError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}`: {}", self.key, self.message)
+    }
+}
+
+impl std::error::Error for QueryStringError {}
+
+/// OpenAPI type of a leaf parameter.
+#[derive(Debug, Clone, Copy)]
+pub enum ParamKind {
+    Integer,
+    Number,
+    String,
+    Boolean,
+    Enum(&'static [&'static str]),
+    /// Comma-separated list, e.g. `exclude.tags=gimmick,meme`
+    List(&'static ParamKind),
+}
+
+impl ParamKind {
+    fn schema(&self) -> Schema {
+        let object = match self {
+            ParamKind::Integer => ObjectBuilder::new().schema_type(Type::Integer),
+            ParamKind::Number => ObjectBuilder::new().schema_type(Type::Number),
+            ParamKind::String => ObjectBuilder::new().schema_type(Type::String),
+            ParamKind::Boolean => ObjectBuilder::new().schema_type(Type::Boolean),
+            ParamKind::Enum(values) => ObjectBuilder::new()
+                .schema_type(Type::String)
+                .enum_values(Some(values.iter().copied())),
+            ParamKind::List(item) => {
+                return Schema::Array(ArrayBuilder::new().items(RefOr::T(item.schema())).build())
+            }
+        };
+        Schema::Object(object.build())
+    }
+}
+
+/// One flat query-string key and how it reads from / writes into `Filters`.
+pub struct QueryParam {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub kind: ParamKind,
+    get: fn(&Filters) -> Option<String>,
+    set: fn(&mut Filters, &str) -> Result<(), String>,
+}
+
+/// Declares a `QueryParam` for `filters.$section.$field` (or a top-level `$field`).
+/// The `list` form maps a `Vec` field to a comma-separated value.
+macro_rules! param {
+    (list $name:literal, $kind:expr, $section:ident . $field:ident, $description:literal) => {
+        QueryParam {
+            name: $name,
+            description: $description,
+            kind: ParamKind::List(&$kind),
+            get: |f: &Filters| {
+                f.$section
+                    .as_ref()
+                    .filter(|s| !s.$field.is_empty())
+                    .map(|s| {
+                        s.$field
+                            .iter()
+                            .map(|v| v.to_string())
+                            .collect::<Vec<_>>()
+                            .join(",")
+                    })
+            },
+            set: |f: &mut Filters, v: &str| {
+                let section = f.$section.get_or_insert_with(Default::default);
+                for item in v.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+                    section
+                        .$field
+                        .push(item.parse().map_err(|e| format!("{}", e))?);
+                }
+                Ok(())
+            },
+        }
+    };
+    ($name:literal, $kind:expr, $section:ident . $field:ident, $description:literal) => {
+        QueryParam {
+            name: $name,
+            description: $description,
+            kind: $kind,
+            get: |f: &Filters| {
+                f.$section
+                    .as_ref()
+                    .and_then(|s| s.$field.as_ref())
+                    .map(|v| v.to_string())
+            },
+            set: |f: &mut Filters, v: &str| {
+                f.$section.get_or_insert_with(Default::default).$field =
+                    Some(v.parse().map_err(|e| format!("{}", e))?);
+                Ok(())
+            },
+        }
+    };
+    ($name:literal, $kind:expr, $field:ident, $description:literal) => {
+        QueryParam {
+            name: $name,
+            description: $description,
+            kind: $kind,
+            get: |f: &Filters| f.$field.as_ref().map(|v| v.to_string()),
+            set: |f: &mut Filters, v: &str| {
+                f.$field = Some(v.parse().map_err(|e| format!("{}", e))?);
+                Ok(())
+            },
+        }
+    };
+}
+
+const COMBINATOR_VALUES: &[&str] = &["and", "or"];
+
+/// Every non-indexed leaf parameter, in encoding order.
+pub const QUERY_PARAMS: &[QueryParam] = &[
+    param!(
+        "rating.type",
+        ParamKind::Enum(RatingType::VALUES),
+        rating.rating_type,
+        "Rating type the rating range applies to"
+    ),
+    param!(
+        "rating.min",
+        ParamKind::Number,
+        rating.rating_min,
+        "Minimum rating"
+    ),
+    param!(
+        "rating.max",
+        ParamKind::Number,
+        rating.rating_max,
+        "Maximum rating"
+    ),
+    QueryParam {
+        name: "skillset.combinator",
+        description: "How skillset constraints are combined (default `and`)",
+        kind: ParamKind::Enum(COMBINATOR_VALUES),
+        get: |f: &Filters| {
+            f.skillsets
+                .as_ref()
+                .and_then(|s| s.combinator)
+                .map(|c| c.as_str().to_string())
+        },
+        set: |f: &mut Filters, v: &str| {
+            f.skillsets.get_or_insert_with(Default::default).combinator = Some(v.parse()?);
+            Ok(())
+        },
+    },
+    param!(
+        "beatmap.search",
+        ParamKind::String,
+        beatmap.search_term,
+        "Full-text search on title, artist, creator, source, tags and difficulty name (`\"exact phrase\"`, `or`, `-excluded`), typo-tolerant on title, artist and creator"
+    ),
+    param!(
+        "beatmap.artist",
+        ParamKind::String,
+        beatmap.artist,
+        "Artist contains"
+    ),
+    param!(
+        "beatmap.title",
+        ParamKind::String,
+        beatmap.title,
+        "Title contains"
+    ),
+    param!(
+        "beatmap.creator",
+        ParamKind::String,
+        beatmap.creator,
+        "Creator contains"
+    ),
+    param!(
+        "beatmap.total_time_min",
+        ParamKind::Integer,
+        beatmap.total_time_min,
+        "Minimum total length in seconds"
+    ),
+    param!(
+        "beatmap.total_time_max",
+        ParamKind::Integer,
+        beatmap.total_time_max,
+        "Maximum total length in seconds"
+    ),
+    param!(
+        "beatmap.bpm_min",
+        ParamKind::Number,
+        beatmap.bpm_min,
+        "Minimum BPM"
+    ),
+    param!(
+        "beatmap.bpm_max",
+        ParamKind::Number,
+        beatmap.bpm_max,
+        "Maximum BPM"
+    ),
+    param!(
+        "technical.od_min",
+        ParamKind::Number,
+        beatmap_technical.od_min,
+        "Minimum Overall Difficulty"
+    ),
+    param!(
+        "technical.od_max",
+        ParamKind::Number,
+        beatmap_technical.od_max,
+        "Maximum Overall Difficulty"
+    ),
+    param!(
+        "technical.status",
+        ParamKind::Enum(RankStatus::VALUES),
+        beatmap_technical.status,
+        "Beatmap rank status"
+    ),
+    param!(
+        "technical.mode",
+        ParamKind::Enum(GameMode::VALUES),
+        beatmap_technical.mode,
+        "Game mode"
+    ),
+    param!(
+        "technical.keycount_min",
+        ParamKind::Integer,
+        beatmap_technical.keycount_min,
+        "Minimum mania key count"
+    ),
+    param!(
+        "technical.keycount_max",
+        ParamKind::Integer,
+        beatmap_technical.keycount_max,
+        "Maximum mania key count"
+    ),
+    param!(
+        "technical.cs_min",
+        ParamKind::Number,
+        beatmap_technical.cs_min,
+        "Minimum Circle Size"
+    ),
+    param!(
+        "technical.cs_max",
+        ParamKind::Number,
+        beatmap_technical.cs_max,
+        "Maximum Circle Size"
+    ),
+    param!(
+        "technical.ar_min",
+        ParamKind::Number,
+        beatmap_technical.ar_min,
+        "Minimum Approach Rate"
+    ),
+    param!(
+        "technical.ar_max",
+        ParamKind::Number,
+        beatmap_technical.ar_max,
+        "Maximum Approach Rate"
+    ),
+    param!(
+        "technical.hp_min",
+        ParamKind::Number,
+        beatmap_technical.hp_min,
+        "Minimum HP drain"
+    ),
+    param!(
+        "technical.hp_max",
+        ParamKind::Number,
+        beatmap_technical.hp_max,
+        "Maximum HP drain"
+    ),
+    param!(
+        "technical.max_combo_min",
+        ParamKind::Integer,
+        beatmap_technical.max_combo_min,
+        "Minimum max combo"
+    ),
+    param!(
+        "technical.max_combo_max",
+        ParamKind::Integer,
+        beatmap_technical.max_combo_max,
+        "Maximum max combo"
+    ),
+    param!(
+        "technical.circles_min",
+        ParamKind::Integer,
+        beatmap_technical.circles_min,
+        "Minimum circle count"
+    ),
+    param!(
+        "technical.circles_max",
+        ParamKind::Integer,
+        beatmap_technical.circles_max,
+        "Maximum circle count"
+    ),
+    param!(
+        "technical.sliders_min",
+        ParamKind::Integer,
+        beatmap_technical.sliders_min,
+        "Minimum slider count"
+    ),
+    param!(
+        "technical.sliders_max",
+        ParamKind::Integer,
+        beatmap_technical.sliders_max,
+        "Maximum slider count"
+    ),
+    param!(
+        "rates.centirate",
+        ParamKind::String,
+        rates.centirate,
+        "Rates to search: `any`, `110`, `110..150`, `110..`, `..150` or `100,110,150` (default `100`)"
+    ),
+    param!(
+        "rates.drain_time_min",
+        ParamKind::Integer,
+        rates.drain_time_min,
+        "Minimum drain time in seconds"
+    ),
+    param!(
+        "rates.drain_time_max",
+        ParamKind::Integer,
+        rates.drain_time_max,
+        "Maximum drain time in seconds"
+    ),
+    param!(
+        "beatmapset.has_video",
+        ParamKind::Boolean,
+        beatmapset.has_video,
+        "Has a video"
+    ),
+    param!(
+        "beatmapset.has_storyboard",
+        ParamKind::Boolean,
+        beatmapset.has_storyboard,
+        "Has a storyboard"
+    ),
+    param!(
+        "beatmapset.is_explicit",
+        ParamKind::Boolean,
+        beatmapset.is_explicit,
+        "Flagged as explicit"
+    ),
+    param!(
+        "beatmapset.is_featured",
+        ParamKind::Boolean,
+        beatmapset.is_featured,
+        "Featured artist"
+    ),
+    param!(
+        "beatmapset.source",
+        ParamKind::String,
+        beatmapset.source,
+        "Exact source (case-insensitive)"
+    ),
+    param!(
+        "beatmapset.source_contains",
+        ParamKind::String,
+        beatmapset.source_contains,
+        "Source contains"
+    ),
+    param!(list
+        "beatmapset.tags",
+        ParamKind::String,
+        beatmapset.tags,
+        "Tags that must all be present"
+    ),
+    param!(
+        "beatmapset.tag_contains",
+        ParamKind::String,
+        beatmapset.tag_contains,
+        "At least one tag contains"
+    ),
+    QueryParam {
+        name: "beatmapset.status_changed_from",
+        description: "Status changed at or after (YYYY-MM-DD[THH:MM:SS])",
+        kind: ParamKind::String,
+        get: |f: &Filters| {
+            f.beatmapset
+                .as_ref()
+                .and_then(|s| s.status_changed_from.as_ref())
+                .map(format_datetime)
+        },
+        set: |f: &mut Filters, v: &str| {
+            f.beatmapset
+                .get_or_insert_with(Default::default)
+                .status_changed_from = Some(parse_datetime(v)?);
+            Ok(())
+        },
+    },
+    QueryParam {
+        name: "beatmapset.status_changed_to",
+        description: "Status changed at or before (YYYY-MM-DD[THH:MM:SS]), a bare date includes that day",
+        kind: ParamKind::String,
+        get: |f: &Filters| {
+            f.beatmapset
+                .as_ref()
+                .and_then(|s| s.status_changed_to.as_ref())
+                .map(format_datetime_end)
+        },
+        set: |f: &mut Filters, v: &str| {
+            f.beatmapset
+                .get_or_insert_with(Default::default)
+                .status_changed_to = Some(parse_datetime_end(v)?);
+            Ok(())
+        },
+    },
+    param!(
+        "beatmapset.status_changed_within_days",
+        ParamKind::Integer,
+        beatmapset.status_changed_within_days,
+        "Status changed within the last N days"
+    ),
+    param!(list
+        "exclude.creators",
+        ParamKind::String,
+        exclude.creators,
+        "Creators to hide (case-insensitive)"
+    ),
+    param!(list
+        "exclude.tags",
+        ParamKind::String,
+        exclude.tags,
+        "Hide beatmapsets carrying any of these tags"
+    ),
+    param!(list
+        "exclude.patterns",
+        ParamKind::Enum(Skillset::VALUES),
+        exclude.patterns,
+        "Hide beatmaps whose main pattern contains any of these skillsets"
+    ),
+    param!(list
+        "exclude.beatmapset_ids",
+        ParamKind::Integer,
+        exclude.beatmapset_ids,
+        "osu! beatmapset ids to hide"
+    ),
+    param!(
+        "sort.key",
+        ParamKind::Enum(SortKey::VALUES),
+        sort.key,
+        "Sort key (default `id`)"
+    ),
+    param!(
+        "sort.order",
+        ParamKind::Enum(SortOrder::VALUES),
+        sort.order,
+        "`asc` or `desc` (default depends on the key)"
+    ),
+    param!(
+        "sort.seed",
+        ParamKind::Integer,
+        sort.seed,
+        "Seed of the `random` sort, keeps pages stable"
+    ),
+    param!(
+        "truncation",
+        ParamKind::String,
+        truncation,
+        "Difficulties kept per set: `all`, `matched`, `easiest:<n>` (plus the hardest, default `easiest:5`) or `closest:<n>:<rating>`"
+    ),
+    param!(
+        "cursor",
+        ParamKind::String,
+        cursor,
+        "Opaque cursor from a previous page, replaces `page`"
+    ),
+    param!(
+        "page",
+        ParamKind::Integer,
+        page,
+        "Page index, starting at 0"
+    ),
+    param!("per_page", ParamKind::Integer, per_page, "Page size"),
+];
+
+/// Indexed skillset leaves, documented as `skillset.{n}.<field>`.
+const SKILLSET_PARAMS: &[(&str, &str, ParamKind)] = &[
+    (
+        "type",
+        "Skillset of the n-th constraint",
+        ParamKind::Enum(Skillset::VALUES),
+    ),
+    (
+        "min",
+        "Minimum value of the n-th skillset",
+        ParamKind::Number,
+    ),
+    (
+        "max",
+        "Maximum value of the n-th skillset",
+        ParamKind::Number,
+    ),
+];
+
+fn set_skillset_field(filter: &mut SkillsetFilter, field: &str, value: &str) -> Result<(), String> {
+    let number = |v: &str| v.parse::<f64>().map_err(|e| e.to_string());
+    match field {
+        "type" => filter.pattern_type = Some(value.parse().map_err(|e| format!("{}", e))?),
+        "min" => filter.pattern_min = Some(number(value)?),
+        "max" => filter.pattern_max = Some(number(value)?),
+        _ => return Err("unknown skillset field".to_string()),
+    }
+    Ok(())
+}
+
+impl Filters {
+    /// Flatten into `(key, value)` pairs, skipping unset fields.
+    pub fn to_query_pairs(&self) -> Vec<(String, String)> {
+        let mut pairs: Vec<(String, String)> = QUERY_PARAMS
+            .iter()
+            .filter_map(|p| (p.get)(self).map(|v| (p.name.to_string(), v)))
+            .collect();
+        if let Some(skillsets) = self.skillsets.as_ref() {
+            for (i, c) in skillsets.constraints.iter().enumerate() {
+                if let Some(pt) = c.pattern_type {
+                    pairs.push((format!("skillset.{}.type", i), pt.to_string()));
+                }
+                if let Some(min) = c.pattern_min {
+                    pairs.push((format!("skillset.{}.min", i), min.to_string()));
+                }
+                if let Some(max) = c.pattern_max {
+                    pairs.push((format!("skillset.{}.max", i), max.to_string()));
+                }
+            }
+        }
+        pairs
+    }
+
+    /// Encode as a percent-encoded query string (without the leading `?`).
+    pub fn to_query_string(&self) -> String {
+        serde_urlencoded::to_string(self.to_query_pairs()).unwrap_or_default()
+    }
+
+    /// Build filters from decoded `(key, value)` pairs. Unknown keys are rejected.
+    pub fn from_query_pairs<K, V>(
+        pairs: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<Self, QueryStringError>
+    where
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let mut filters = Filters::default();
+        // Indexed constraints may arrive in any order; keep them sorted by index
+        let mut skillsets: Vec<(usize, SkillsetFilter)> = Vec::new();
+
+        for (key, value) in pairs {
+            let (key, value) = (key.as_ref(), value.as_ref());
+            if value.is_empty() {
+                continue;
+            }
+            if let Some(rest) = key.strip_prefix("skillset.") {
+                if let Some((index, field)) = rest.split_once('.') {
+                    let index: usize = index
+                        .parse()
+                        .map_err(|_| QueryStringError::new(key, "invalid skillset index"))?;
+                    let pos = match skillsets.iter().position(|(i, _)| *i == index) {
+                        Some(pos) => pos,
+                        None => {
+                            skillsets.push((index, SkillsetFilter::default()));
+                            skillsets.len() - 1
+                        }
+                    };
+                    set_skillset_field(&mut skillsets[pos].1, field, value)
+                        .map_err(|e| QueryStringError::new(key, e))?;
+                    continue;
+                }
+            }
+            let param = QUERY_PARAMS
+                .iter()
+                .find(|p| p.name == key)
+                .ok_or_else(|| QueryStringError::new(key, "unknown parameter"))?;
+            (param.set)(&mut filters, value).map_err(|e| QueryStringError::new(key, e))?;
+        }
+
+        if !skillsets.is_empty() {
+            skillsets.sort_by_key(|(i, _)| *i);
+            filters
+                .skillsets
+                .get_or_insert_with(Default::default)
+                .constraints = skillsets.into_iter().map(|(_, c)| c).collect();
+        }
+        Ok(filters)
+    }
+
+    /// Decode a query string such as `rating.type=osu&rating.min=3` (a leading `?` is allowed).
+    pub fn from_query_string(query: &str) -> Result<Self, QueryStringError> {
+        let query = query.strip_prefix('?').unwrap_or(query);
+        let pairs: Vec<(String, String)> = serde_urlencoded::from_str(query)
+            .map_err(|e| QueryStringError::new("", e.to_string()))?;
+        Self::from_query_pairs(pairs)
+    }
+}
+
+impl IntoParams for Filters {
+    fn into_params(parameter_in_provider: impl Fn() -> Option<ParameterIn>) -> Vec<Parameter> {
+        let parameter_in = parameter_in_provider().unwrap_or(ParameterIn::Query);
+        let leaf = |name: String, description: &str, kind: ParamKind| {
+            let builder = ParameterBuilder::new()
+                .name(name)
+                .parameter_in(parameter_in.clone())
+                .description(Some(description))
+                .required(Required::False)
+                .schema(Some(kind.schema()));
+            match kind {
+                ParamKind::List(_) => builder
+                    .style(Some(ParameterStyle::Form))
+                    .explode(Some(false))
+                    .build(),
+                _ => builder.build(),
+            }
+        };
+
+        let mut params: Vec<Parameter> = QUERY_PARAMS
+            .iter()
+            .map(|p| leaf(p.name.to_string(), p.description, p.kind))
+            .collect();
+        params.extend(SKILLSET_PARAMS.iter().map(|(field, description, kind)| {
+            leaf(format!("skillset.{{n}}.{}", field), description, *kind)
+        }));
+        params
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filters::{CentirateSelection, SkillsetCombinator, TruncationPolicy};
+    use chrono::NaiveDate;
+
+    fn full_filters() -> Filters {
+        let day = |d| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        Filters::builder()
+            .rating(RatingType::Etterna, Some(20.5), Some(30.0))
+            .skillset(Skillset::Stream, Some(20.0), None)
+            .skillset(Skillset::Jackspeed, None, Some(15.0))
+            .skillset_combinator(SkillsetCombinator::Or)
+            .search("\"big black\" camellia -remix")
+            .creator("Someone")
+            .bpm(Some(180.0), Some(220.0))
+            .status(RankStatus::Ranked)
+            .mode(GameMode::Mania)
+            .keycount(Some(4), Some(7))
+            .centirate(CentirateSelection::List(vec![100, 110, 150]))
+            .has_video(false)
+            .source("touhou")
+            .tag("vocaloid")
+            .tag_contains("voca")
+            .status_changed(
+                Some(day(1).and_hms_opt(0, 0, 0).unwrap()),
+                Some(day(31).and_hms_micro_opt(23, 59, 59, 999_999).unwrap()),
+            )
+            .exclude_creator("Other Mapper")
+            .exclude_tag("gimmick")
+            .exclude_pattern(Skillset::Chordjack)
+            .exclude_beatmapset(123456)
+            .random_sort(-42)
+            .truncation(TruncationPolicy::ClosestToRating { n: 4, target: 25.0 })
+            .per_page(20)
+            .build()
+    }
+
+    #[test]
+    fn round_trips_every_field() {
+        let filters = full_filters();
+        let query = filters.to_query_string();
+        let decoded = Filters::from_query_string(&query).unwrap();
+        assert_eq!(decoded.to_query_string(), query);
+
+        let skillsets = decoded.skillsets.unwrap();
+        assert_eq!(skillsets.combinator, Some(SkillsetCombinator::Or));
+        assert_eq!(skillsets.constraints.len(), 2);
+        assert_eq!(skillsets.constraints[1].pattern_max, Some(15.0));
+        assert_eq!(
+            decoded.rates.unwrap().centirate,
+            Some(CentirateSelection::List(vec![100, 110, 150]))
+        );
+        assert_eq!(
+            decoded.beatmap.unwrap().search_term.as_deref(),
+            Some("\"big black\" camellia -remix")
+        );
+        assert_eq!(decoded.sort.unwrap().seed, Some(-42));
+        assert_eq!(
+            decoded.truncation,
+            Some(TruncationPolicy::ClosestToRating { n: 4, target: 25.0 })
+        );
+    }
+
+    #[test]
+    fn empty_filters_encode_to_nothing() {
+        assert_eq!(Filters::default().to_query_string(), "");
+        assert_eq!(
+            Filters::from_query_string("?").unwrap().to_query_string(),
+            ""
+        );
+    }
+
+    #[test]
+    fn keeps_skillsets_in_index_order() {
+        let filters = Filters::from_query_string(
+            "skillset.1.type=jackspeed&skillset.0.type=stream&skillset.0.min=20",
+        )
+        .unwrap();
+        let constraints = filters.skillsets.unwrap().constraints;
+        assert_eq!(constraints[0].pattern_type, Some(Skillset::Stream));
+        assert_eq!(constraints[0].pattern_min, Some(20.0));
+        assert_eq!(constraints[1].pattern_type, Some(Skillset::Jackspeed));
+    }
+
+    #[test]
+    fn date_only_upper_bound_includes_the_day() {
+        let filters =
+            Filters::from_query_string("beatmapset.status_changed_to=2024-06-30").unwrap();
+        let to = filters.beatmapset.unwrap().status_changed_to.unwrap();
+        assert_eq!(to.to_string(), "2024-06-30 23:59:59.999999");
+    }
+
+    #[test]
+    fn reports_the_offending_key() {
+        let err = Filters::from_query_string("rating.min=3&raitng.max=4").unwrap_err();
+        assert_eq!(err.key, "raitng.max");
+
+        let err = Filters::from_query_string("skillset.x.type=stream").unwrap_err();
+        assert_eq!(err.key, "skillset.x.type");
+
+        let err = Filters::from_query_string("beatmap.bpm_min=fast").unwrap_err();
+        assert_eq!(err.key, "beatmap.bpm_min");
+        assert_eq!(err.message, "fast".parse::<f64>().unwrap_err().to_string());
+    }
+}
@@ -0,0 +1,148 @@
+//! Compact, URL-safe tokens for sharing and storing [`Filters`].
+//!
+//! A token is `v<version>.<payload>`. Version 1 carries the flat query string
+//! (see [`super::query_string`]) encoded as unpadded base64url, so a token
+//! only holds the keys that are set and keeps decoding when new fields are
+//! added. Renaming or removing a key must bump the version and keep a decoder
+//! for the old one here.
+
+use super::{Filters, QueryStringError};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use std::fmt;
+
+/// Version written by [`Filters::to_token`].
+pub const TOKEN_VERSION: u32 = 1;
+
+/// Error raised when a token cannot be decoded into `Filters`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenError {
+    /// Missing `v<n>.` prefix or payload that is not base64url / UTF-8
+    Malformed(String),
+    /// Token written by a newer (or unknown) encoder
+    UnsupportedVersion(String),
+    /// The payload decoded but does not describe valid filters
+    InvalidFilters(QueryStringError),
+}
+
+impl fmt::Display for TokenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenError::Malformed(message) => write!(f, "malformed filter token: {}", message),
+            TokenError::UnsupportedVersion(version) => {
+                write!(f, "unsupported filter token version `{}`", version)
+            }
+            TokenError::InvalidFilters(e) => write!(f, "invalid filter token: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for TokenError {}
+
+impl Filters {
+    /// Encode these filters into a shareable token, e.g. `v1.cmF0aW5nLm1pbj0z`.
+    ///
+    /// `page` and `cursor` are left out: a token names a search, and opening
+    /// it starts from the first page.
+    pub fn to_token(&self) -> String {
+        let mut search = self.clone();
+        search.page = None;
+        search.cursor = None;
+        format!(
+            "v{}.{}",
+            TOKEN_VERSION,
+            URL_SAFE_NO_PAD.encode(search.to_query_string())
+        )
+    }
+
+    /// Decode a token produced by [`Filters::to_token`], from any known version.
+    pub fn from_token(token: &str) -> Result<Self, TokenError> {
+        let (version, payload) = token
+            .trim()
+            .strip_prefix('v')
+            .and_then(|rest| rest.split_once('.'))
+            .ok_or_else(|| TokenError::Malformed("expected `v<version>.<payload>`".to_string()))?;
+
+        match version {
+            "1" => {
+                let bytes = URL_SAFE_NO_PAD
+                    .decode(payload)
+                    .map_err(|e| TokenError::Malformed(e.to_string()))?;
+                let query =
+                    String::from_utf8(bytes).map_err(|e| TokenError::Malformed(e.to_string()))?;
+                Filters::from_query_string(&query).map_err(TokenError::InvalidFilters)
+            }
+            _ => Err(TokenError::UnsupportedVersion(version.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enums::{RatingType, Skillset};
+    use crate::filters::CentirateSelection;
+
+    fn filters() -> Filters {
+        Filters::builder()
+            .rating(RatingType::Osu, Some(3.0), None)
+            .skillset(Skillset::Stream, Some(20.0), Some(25.5))
+            .search("\"big black\" é")
+            .centirate(CentirateSelection::Range {
+                min: Some(110),
+                max: None,
+            })
+            .per_page(20)
+            .build()
+    }
+
+    #[test]
+    fn round_trips() {
+        let filters = filters();
+        let token = filters.to_token();
+        assert!(token.starts_with("v1."));
+        assert!(token[3..]
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        let decoded = Filters::from_token(&token).unwrap();
+        assert_eq!(decoded.to_query_string(), filters.to_query_string());
+    }
+
+    #[test]
+    fn leaves_out_page_and_cursor() {
+        let mut filters = filters();
+        filters.page = Some(3);
+        filters.cursor = Some("abc".to_string());
+        let decoded = Filters::from_token(&filters.to_token()).unwrap();
+        assert_eq!(decoded.page, None);
+        assert_eq!(decoded.cursor, None);
+        assert_eq!(decoded.per_page, Some(20));
+    }
+
+    #[test]
+    fn decodes_a_stored_v1_token() {
+        let decoded = Filters::from_token(" v1.cmF0aW5nLm1pbj0z ").unwrap();
+        assert_eq!(decoded.rating.unwrap().rating_min, Some(3.0));
+    }
+
+    #[test]
+    fn rejects_bad_tokens() {
+        assert!(matches!(
+            Filters::from_token("cmF0aW5nLm1pbj0z"),
+            Err(TokenError::Malformed(_))
+        ));
+        assert!(matches!(
+            Filters::from_token("v1.not base64!"),
+            Err(TokenError::Malformed(_))
+        ));
+        assert_eq!(
+            Filters::from_token("v2.cmF0aW5nLm1pbj0z").unwrap_err(),
+            TokenError::UnsupportedVersion("2".to_string())
+        );
+        // `raitng.min=3`
+        assert!(matches!(
+            Filters::from_token("v1.cmFpdG5nLm1pbj0z"),
+            Err(TokenError::InvalidFilters(_))
+        ));
+    }
+}
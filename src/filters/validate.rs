@@ -0,0 +1,484 @@
+//! Validation and normalization of [`Filters`] before they reach SQL.
+//!
+//! Errors name the offending field by its flat query-string key
+//! (`rating.min`, `skillset.1.max`, ...) so clients can map them back to
+//! their inputs.
+
+use super::{CentirateSelection, Filters, SearchCursor, SortKey, TruncationPolicy};
+use serde::Serialize;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::ops::Deref;
+use utoipa::ToSchema;
+
+/// Page size used when `per_page` is not set.
+pub const DEFAULT_PER_PAGE: usize = 9;
+/// Largest accepted page size, bigger values are clamped.
+pub const MAX_PER_PAGE: usize = 100;
+/// Last accepted page number, deeper results are walked with cursors.
+pub const MAX_PAGE: usize = 10_000;
+
+/// Highest mania key count (stored as CS).
+const MAX_KEYCOUNT: i32 = 18;
+/// Highest circle size; mania key counts go through `keycount_*` instead.
+const MAX_CIRCLE_SIZE: f64 = 10.0;
+
+/// What is wrong with a filter field
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterErrorKind {
+    /// The value cannot be decoded or does not apply to the other fields
+    Invalid,
+    /// The minimum of a range is greater than its maximum
+    InvertedRange,
+    /// The value is outside the accepted bounds (negative, too large, ...)
+    OutOfBounds,
+    /// NaN or infinite number
+    NotFinite,
+    /// A bound was given without the field it applies to
+    Missing,
+}
+
+/// One invalid field of a `Filters` value.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+pub struct FilterFieldError {
+    /// Flat query-string key, e.g. `rating.min`
+    pub field: String,
+    pub kind: FilterErrorKind,
+    pub message: String,
+}
+
+impl fmt::Display for FilterFieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}`: {}", self.field, self.message)
+    }
+}
+
+impl std::error::Error for FilterFieldError {}
+
+#[derive(Default)]
+struct Checker {
+    errors: Vec<FilterFieldError>,
+}
+
+impl Checker {
+    fn push(&mut self, field: impl Into<String>, kind: FilterErrorKind, message: String) {
+        self.errors.push(FilterFieldError {
+            field: field.into(),
+            kind,
+            message,
+        });
+    }
+
+    /// Check `lo <= min <= max <= hi` for an integer range.
+    fn int_range(
+        &mut self,
+        keys: (&str, &str),
+        min: Option<i32>,
+        max: Option<i32>,
+        lo: i32,
+        hi: Option<i32>,
+    ) {
+        for (key, value) in [(keys.0, min), (keys.1, max)] {
+            let Some(value) = value else { continue };
+            if value < lo {
+                self.push(
+                    key,
+                    FilterErrorKind::OutOfBounds,
+                    format!("must be at least {}", lo),
+                );
+            } else if let Some(hi) = hi.filter(|hi| value > *hi) {
+                self.push(
+                    key,
+                    FilterErrorKind::OutOfBounds,
+                    format!("must be at most {}", hi),
+                );
+            }
+        }
+        if let (Some(min), Some(max)) = (min, max) {
+            if min > max {
+                self.inverted(keys, min, max);
+            }
+        }
+    }
+
+    /// Same as `int_range`, also rejecting NaN and infinities.
+    fn float_range(
+        &mut self,
+        keys: (&str, &str),
+        min: Option<f64>,
+        max: Option<f64>,
+        lo: f64,
+        hi: Option<f64>,
+    ) {
+        let mut valid = true;
+        for (key, value) in [(keys.0, min), (keys.1, max)] {
+            let Some(value) = value else { continue };
+            valid &= if !value.is_finite() {
+                self.push(
+                    key,
+                    FilterErrorKind::NotFinite,
+                    "must be a finite number".to_string(),
+                );
+                false
+            } else if value < lo {
+                self.push(
+                    key,
+                    FilterErrorKind::OutOfBounds,
+                    format!("must be at least {}", lo),
+                );
+                false
+            } else if let Some(hi) = hi.filter(|hi| value > *hi) {
+                self.push(
+                    key,
+                    FilterErrorKind::OutOfBounds,
+                    format!("must be at most {}", hi),
+                );
+                false
+            } else {
+                true
+            };
+        }
+        if let (true, Some(min), Some(max)) = (valid, min, max) {
+            if min > max {
+                self.inverted(keys, min, max);
+            }
+        }
+    }
+
+    fn inverted(&mut self, keys: (&str, &str), min: impl fmt::Display, max: impl fmt::Display) {
+        self.push(
+            keys.0,
+            FilterErrorKind::InvertedRange,
+            format!("minimum {} is greater than maximum {}", min, max),
+        );
+    }
+}
+
+/// Trim a text filter and drop it when nothing is left.
+fn normalize_text(value: &mut Option<String>) {
+    *value = value
+        .take()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty());
+}
+
+/// Trim every entry of a list filter, dropping the blank ones.
+fn normalize_list(values: &mut Vec<String>) {
+    for value in values.iter_mut() {
+        *value = value.trim().to_string();
+    }
+    values.retain(|v| !v.is_empty());
+}
+
+impl Filters {
+    /// Fill in the paging defaults, clamp `per_page` to `1..=MAX_PER_PAGE`,
+    /// draw a seed for an unseeded `random` sort and drop blank text filters.
+    ///
+    /// The seed comes from `cursor` when there is one, otherwise it is drawn.
+    /// It stays in `sort.seed` (and in the cursors), so callers can echo it
+    /// back and the client can reproduce the page.
+    pub fn normalize(&mut self) {
+        self.page.get_or_insert(0);
+        self.per_page = Some(
+            self.per_page
+                .unwrap_or(DEFAULT_PER_PAGE)
+                .clamp(1, MAX_PER_PAGE),
+        );
+        // A follow-up page keeps the seed of its cursor
+        let cursor_seed = self
+            .cursor
+            .as_deref()
+            .and_then(|token| SearchCursor::decode(token).ok())
+            .and_then(|cursor| cursor.seed);
+        if let Some(sort) = self.sort.as_mut() {
+            if sort.key() == SortKey::Random && sort.seed.is_none() {
+                sort.seed = Some(
+                    cursor_seed
+                        .unwrap_or_else(|| RandomState::new().build_hasher().finish() as i64),
+                );
+            }
+        }
+
+        if let Some(beatmap) = self.beatmap.as_mut() {
+            normalize_text(&mut beatmap.search_term);
+            normalize_text(&mut beatmap.artist);
+            normalize_text(&mut beatmap.title);
+            normalize_text(&mut beatmap.creator);
+        }
+        if let Some(bs) = self.beatmapset.as_mut() {
+            normalize_text(&mut bs.source);
+            normalize_text(&mut bs.source_contains);
+            normalize_text(&mut bs.tag_contains);
+            normalize_list(&mut bs.tags);
+        }
+        if let Some(exclude) = self.exclude.as_mut() {
+            normalize_list(&mut exclude.creators);
+            normalize_list(&mut exclude.tags);
+        }
+    }
+
+    /// Check every range and bound, returning all invalid fields at once.
+    ///
+    /// Inverted ranges are rejected rather than swapped, since they usually
+    /// mean the client mixed up two inputs.
+    pub fn validate(&self) -> Result<(), Vec<FilterFieldError>> {
+        let mut check = Checker::default();
+
+        if let Some(rating) = self.rating.as_ref() {
+            check.float_range(
+                ("rating.min", "rating.max"),
+                rating.rating_min,
+                rating.rating_max,
+                0.0,
+                None,
+            );
+        }
+
+        if let Some(skillsets) = self.skillsets.as_ref() {
+            for (i, skill) in skillsets.constraints.iter().enumerate() {
+                let min_key = format!("skillset.{}.min", i);
+                let max_key = format!("skillset.{}.max", i);
+                check.float_range(
+                    (&min_key, &max_key),
+                    skill.pattern_min,
+                    skill.pattern_max,
+                    0.0,
+                    None,
+                );
+                let has_bounds = skill.pattern_min.is_some() || skill.pattern_max.is_some();
+                if skill.pattern_type.is_none() && has_bounds {
+                    check.push(
+                        format!("skillset.{}.type", i),
+                        FilterErrorKind::Missing,
+                        "a skillset is required when min or max is set".to_string(),
+                    );
+                }
+            }
+        }
+
+        if let Some(beatmap) = self.beatmap.as_ref() {
+            check.int_range(
+                ("beatmap.total_time_min", "beatmap.total_time_max"),
+                beatmap.total_time_min,
+                beatmap.total_time_max,
+                0,
+                None,
+            );
+            check.float_range(
+                ("beatmap.bpm_min", "beatmap.bpm_max"),
+                beatmap.bpm_min,
+                beatmap.bpm_max,
+                0.0,
+                None,
+            );
+        }
+
+        if let Some(technical) = self.beatmap_technical.as_ref() {
+            check.float_range(
+                ("technical.od_min", "technical.od_max"),
+                technical.od_min,
+                technical.od_max,
+                0.0,
+                Some(10.0),
+            );
+            check.int_range(
+                ("technical.keycount_min", "technical.keycount_max"),
+                technical.keycount_min,
+                technical.keycount_max,
+                1,
+                Some(MAX_KEYCOUNT),
+            );
+            check.float_range(
+                ("technical.cs_min", "technical.cs_max"),
+                technical.cs_min,
+                technical.cs_max,
+                0.0,
+                Some(MAX_CIRCLE_SIZE),
+            );
+            check.float_range(
+                ("technical.ar_min", "technical.ar_max"),
+                technical.ar_min,
+                technical.ar_max,
+                0.0,
+                Some(10.0),
+            );
+            check.float_range(
+                ("technical.hp_min", "technical.hp_max"),
+                technical.hp_min,
+                technical.hp_max,
+                0.0,
+                Some(10.0),
+            );
+            check.int_range(
+                ("technical.max_combo_min", "technical.max_combo_max"),
+                technical.max_combo_min,
+                technical.max_combo_max,
+                0,
+                None,
+            );
+            check.int_range(
+                ("technical.circles_min", "technical.circles_max"),
+                technical.circles_min,
+                technical.circles_max,
+                0,
+                None,
+            );
+            check.int_range(
+                ("technical.sliders_min", "technical.sliders_max"),
+                technical.sliders_min,
+                technical.sliders_max,
+                0,
+                None,
+            );
+        }
+
+        if let Some(rates) = self.rates.as_ref() {
+            match rates.centirate.as_ref() {
+                Some(CentirateSelection::Exact(centirate)) => check.int_range(
+                    ("rates.centirate", "rates.centirate"),
+                    Some(*centirate),
+                    None,
+                    1,
+                    None,
+                ),
+                Some(CentirateSelection::Range { min, max }) => {
+                    check.int_range(("rates.centirate", "rates.centirate"), *min, *max, 1, None)
+                }
+                Some(CentirateSelection::List(centirates)) => {
+                    if centirates.is_empty() {
+                        check.push(
+                            "rates.centirate",
+                            FilterErrorKind::Missing,
+                            "must list at least one centirate".to_string(),
+                        );
+                    }
+                    for centirate in centirates {
+                        check.int_range(
+                            ("rates.centirate", "rates.centirate"),
+                            Some(*centirate),
+                            None,
+                            1,
+                            None,
+                        );
+                    }
+                }
+                Some(CentirateSelection::Any) | None => {}
+            }
+            check.int_range(
+                ("rates.drain_time_min", "rates.drain_time_max"),
+                rates.drain_time_min,
+                rates.drain_time_max,
+                0,
+                None,
+            );
+        }
+
+        if let Some(bs) = self.beatmapset.as_ref() {
+            if let (Some(from), Some(to)) = (bs.status_changed_from, bs.status_changed_to) {
+                if from > to {
+                    check.inverted(
+                        (
+                            "beatmapset.status_changed_from",
+                            "beatmapset.status_changed_to",
+                        ),
+                        from,
+                        to,
+                    );
+                }
+            }
+            check.int_range(
+                (
+                    "beatmapset.status_changed_within_days",
+                    "beatmapset.status_changed_within_days",
+                ),
+                bs.status_changed_within_days,
+                None,
+                0,
+                None,
+            );
+        }
+
+        if let Some(TruncationPolicy::ClosestToRating { n, target }) = self.truncation.as_ref() {
+            if *n == 0 {
+                check.push(
+                    "truncation",
+                    FilterErrorKind::OutOfBounds,
+                    "must keep at least one difficulty".to_string(),
+                );
+            }
+            check.float_range(("truncation", "truncation"), Some(*target), None, 0.0, None);
+        }
+
+        if let Err(e) = self.decoded_cursor() {
+            check.push("cursor", FilterErrorKind::Invalid, e.to_string());
+        }
+
+        if self.page.is_some_and(|page| page > MAX_PAGE) {
+            check.push(
+                "page",
+                FilterErrorKind::OutOfBounds,
+                format!("must be at most {}, use a cursor beyond", MAX_PAGE),
+            );
+        }
+
+        if let Some(per_page) = self.per_page {
+            if !(1..=MAX_PER_PAGE).contains(&per_page) {
+                check.push(
+                    "per_page",
+                    FilterErrorKind::OutOfBounds,
+                    format!("must be between 1 and {}", MAX_PER_PAGE),
+                );
+            }
+        }
+
+        if check.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(check.errors)
+        }
+    }
+
+    /// Rows before the current page, saturating rather than overflowing.
+    pub fn offset(&self) -> i64 {
+        let page = self.page.unwrap_or(0).min(MAX_PAGE) as i64;
+        let per_page = self.per_page.unwrap_or(DEFAULT_PER_PAGE).min(MAX_PER_PAGE) as i64;
+        page.saturating_mul(per_page)
+    }
+
+    /// Normalize then validate, the usual entry point for request input.
+    pub fn validated(mut self) -> Result<ValidFilters, Vec<FilterFieldError>> {
+        self.normalize();
+        self.validate()?;
+        Ok(ValidFilters(self))
+    }
+}
+
+/// `Filters` that went through `Filters::validated`: normalized, without
+/// field errors. The search queries only take this, so every consumer
+/// rejects bad input the same way, e.g. with `ApiResponse::invalid_filters`.
+#[derive(Debug, Clone)]
+pub struct ValidFilters(Filters);
+
+impl ValidFilters {
+    pub fn into_inner(self) -> Filters {
+        self.0
+    }
+}
+
+impl Deref for ValidFilters {
+    type Target = Filters;
+
+    fn deref(&self) -> &Filters {
+        &self.0
+    }
+}
+
+impl TryFrom<Filters> for ValidFilters {
+    type Error = Vec<FilterFieldError>;
+
+    fn try_from(filters: Filters) -> Result<Self, Self::Error> {
+        filters.validated()
+    }
+}
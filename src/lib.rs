@@ -2,5 +2,6 @@
 // Ce module contiendra les structures de données pour la communication entre les couches
 
 pub mod common;
+pub mod enums;
 pub mod filters;
 pub mod models;
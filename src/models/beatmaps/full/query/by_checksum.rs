@@ -0,0 +1,162 @@
+use crate::enums::{GameMode, Known, RankStatus, RatingType};
+use crate::models::beatmaps::full::types::Beatmapset;
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+use super::common::{group_beatmapset_detail_rows, BeatmapsetDetailRow};
+
+/// Full beatmapset containing the difficulty whose `.osu` file has this MD5
+/// checksum (`rates.osu_hash`). The hash of a rate-changed file resolves to
+/// its centirate: every difficulty then carries its rate at that centirate.
+pub async fn find_full_by_checksum(
+    pool: &PgPool,
+    checksum: &str,
+) -> Result<Option<Beatmapset>, sqlx::Error> {
+    let mut found = find_full_by_checksums(pool, &[checksum]).await?;
+    Ok(found.remove(checksum).flatten())
+}
+
+/// `find_full_by_checksum` for many checksums, keyed by the checksums as
+/// given. Every requested checksum has an entry, `None` when no rate has it.
+pub async fn find_full_by_checksums<S: AsRef<str>>(
+    pool: &PgPool,
+    checksums: &[S],
+) -> Result<HashMap<String, Option<Beatmapset>>, sqlx::Error> {
+    if checksums.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let normalized: Vec<String> = checksums
+        .iter()
+        .map(|c| c.as_ref().trim().to_ascii_lowercase())
+        .collect();
+    let hits = sqlx::query!(
+        r#"
+        SELECT hr.osu_hash, hr.centirate, hb.beatmapset_id AS "beatmapset_id!"
+        FROM rates hr
+        INNER JOIN beatmap hb ON hb.id = hr.beatmap_id
+        WHERE hr.osu_hash = ANY($1) AND hb.beatmapset_id IS NOT NULL
+        "#,
+        &normalized
+    )
+    .fetch_all(pool)
+    .await?;
+
+    // One detail query per centirate hit, usually just 100
+    let mut by_centirate: HashMap<i32, Vec<i32>> = HashMap::new();
+    for hit in &hits {
+        by_centirate
+            .entry(hit.centirate)
+            .or_default()
+            .push(hit.beatmapset_id);
+    }
+    let mut sets: HashMap<(i32, i32), Beatmapset> = HashMap::new();
+    for (centirate, beatmapset_ids) in by_centirate {
+        for set in find_full_at_centirate(pool, &beatmapset_ids, centirate).await? {
+            if let Some(id) = set.id {
+                sets.insert((id, centirate), set);
+            }
+        }
+    }
+
+    let found: HashMap<&str, &Beatmapset> = hits
+        .iter()
+        .filter_map(|hit| {
+            let set = sets.get(&(hit.beatmapset_id, hit.centirate))?;
+            Some((hit.osu_hash.as_str(), set))
+        })
+        .collect();
+    Ok(checksums
+        .iter()
+        .zip(&normalized)
+        .map(|(checksum, hash)| {
+            let set = found.get(hash.as_str()).map(|set| (*set).clone());
+            (checksum.as_ref().to_string(), set)
+        })
+        .collect())
+}
+
+/// Full beatmapsets with every difficulty's rate at `centirate`.
+async fn find_full_at_centirate(
+    pool: &PgPool,
+    beatmapset_ids: &[i32],
+    centirate: i32,
+) -> Result<Vec<Beatmapset>, sqlx::Error> {
+    let rows = sqlx::query_as!(
+        BeatmapsetDetailRow,
+        r#"
+        SELECT
+            -- beatmapset
+            bs.id                    AS bs_id,
+            bs.osu_id                AS bs_osu_id,
+            bs.artist                AS bs_artist,
+            bs.artist_unicode        AS bs_artist_unicode,
+            bs.title                 AS bs_title,
+            bs.title_unicode         AS bs_title_unicode,
+            bs.creator               AS bs_creator,
+            bs.source                AS bs_source,
+            bs.tags                  AS bs_tags,
+            bs.has_video             AS bs_has_video,
+            bs.has_storyboard        AS bs_has_storyboard,
+            bs.is_explicit           AS bs_is_explicit,
+            bs.is_featured           AS bs_is_featured,
+            bs.cover_url             AS bs_cover_url,
+            bs.preview_url           AS bs_preview_url,
+            bs.osu_file_url          AS bs_osu_file_url,
+            bs.osu_status_changed_at AS bs_osu_status_changed_at,
+            -- beatmap
+            b.id                     AS b_id,
+            b.osu_id                 AS b_osu_id,
+            b.beatmapset_id          AS b_beatmapset_id,
+            b.difficulty             AS b_difficulty,
+            b.count_circles          AS b_count_circles,
+            b.count_sliders          AS b_count_sliders,
+            b.count_spinners         AS b_count_spinners,
+            b.max_combo              AS b_max_combo,
+            b.main_pattern           AS b_main_pattern,
+            b.cs                     AS b_cs,
+            b.ar                     AS b_ar,
+            b.od                     AS b_od,
+            b.hp                     AS b_hp,
+            b.mode                   AS "b_mode: Known<GameMode>",
+            b.status                 AS "b_status: Known<RankStatus>",
+
+            -- rates at the checksum's own centirate (its `hr.centirate`), may be missing
+            r.id                     AS "r_id?",
+            r.osu_hash               AS "r_osu_hash?",
+            r.centirate              AS "r_centirate?",
+            r.drain_time             AS "r_drain_time?",
+            r.total_time             AS "r_total_time?",
+            r.bpm                    AS "r_bpm?",
+
+            -- rating (optional)
+            br.id                    AS "br_id?",
+            br.rates_id              AS "br_rates_id?",
+            br.rating                AS "br_rating?",
+            br.rating_type           AS "br_rating_type?: Known<RatingType>",
+
+            -- mania rating (optional)
+            bmr.id                   AS "bmr_id?",
+            bmr.stream               AS "bmr_stream?",
+            bmr.jumpstream           AS "bmr_jumpstream?",
+            bmr.handstream           AS "bmr_handstream?",
+            bmr.stamina              AS "bmr_stamina?",
+            bmr.jackspeed            AS "bmr_jackspeed?",
+            bmr.chordjack            AS "bmr_chordjack?",
+            bmr.technical            AS "bmr_technical?"
+        FROM beatmapset bs
+        INNER JOIN beatmap b ON bs.id = b.beatmapset_id
+        LEFT JOIN rates r ON r.beatmap_id = b.id AND r.centirate = $2
+        LEFT JOIN beatmap_rating br ON br.rates_id = r.id
+        LEFT JOIN beatmap_mania_rating bmr ON bmr.rating_id = br.id
+        WHERE bs.id = ANY($1)
+        ORDER BY bs.id ASC, b.id ASC, r.id ASC, br.id ASC
+        "#,
+        beatmapset_ids,
+        centirate
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(group_beatmapset_detail_rows(rows))
+}
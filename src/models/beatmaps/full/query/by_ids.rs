@@ -0,0 +1,113 @@
+use crate::enums::{GameMode, Known, RankStatus, RatingType};
+use crate::filters::CentirateSelection;
+use crate::models::beatmaps::full::types::Beatmapset;
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+use super::common::{group_beatmapset_detail_rows, selection_params, BeatmapsetDetailRow};
+
+/// Full beatmapsets with the `rates` selected for internal beatmapset ids,
+/// returned in the order of `beatmapset_ids`. Unknown ids are skipped.
+pub async fn find_full_by_ids(
+    pool: &PgPool,
+    beatmapset_ids: &[i32],
+    rates: &CentirateSelection,
+) -> Result<Vec<Beatmapset>, sqlx::Error> {
+    if beatmapset_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let (centirates, min, max) = selection_params(rates);
+
+    let rows = sqlx::query_as!(
+        BeatmapsetDetailRow,
+        r#"
+        SELECT
+            -- beatmapset
+            bs.id                    AS bs_id,
+            bs.osu_id                AS bs_osu_id,
+            bs.artist                AS bs_artist,
+            bs.artist_unicode        AS bs_artist_unicode,
+            bs.title                 AS bs_title,
+            bs.title_unicode         AS bs_title_unicode,
+            bs.creator               AS bs_creator,
+            bs.source                AS bs_source,
+            bs.tags                  AS bs_tags,
+            bs.has_video             AS bs_has_video,
+            bs.has_storyboard        AS bs_has_storyboard,
+            bs.is_explicit           AS bs_is_explicit,
+            bs.is_featured           AS bs_is_featured,
+            bs.cover_url             AS bs_cover_url,
+            bs.preview_url           AS bs_preview_url,
+            bs.osu_file_url          AS bs_osu_file_url,
+            bs.osu_status_changed_at AS bs_osu_status_changed_at,
+            -- beatmap
+            b.id                     AS b_id,
+            b.osu_id                 AS b_osu_id,
+            b.beatmapset_id          AS b_beatmapset_id,
+            b.difficulty             AS b_difficulty,
+            b.count_circles          AS b_count_circles,
+            b.count_sliders          AS b_count_sliders,
+            b.count_spinners         AS b_count_spinners,
+            b.max_combo              AS b_max_combo,
+            b.main_pattern           AS b_main_pattern,
+            b.cs                     AS b_cs,
+            b.ar                     AS b_ar,
+            b.od                     AS b_od,
+            b.hp                     AS b_hp,
+            b.mode                   AS "b_mode: Known<GameMode>",
+            b.status                 AS "b_status: Known<RankStatus>",
+
+            -- rates in the selection, may be missing
+            r.id                     AS "r_id?",
+            r.osu_hash               AS "r_osu_hash?",
+            r.centirate              AS "r_centirate?",
+            r.drain_time             AS "r_drain_time?",
+            r.total_time             AS "r_total_time?",
+            r.bpm                    AS "r_bpm?",
+
+            -- rating (optional)
+            br.id                    AS "br_id?",
+            br.rates_id              AS "br_rates_id?",
+            br.rating                AS "br_rating?",
+            br.rating_type           AS "br_rating_type?: Known<RatingType>",
+
+            -- mania rating (optional)
+            bmr.id                   AS "bmr_id?",
+            bmr.stream               AS "bmr_stream?",
+            bmr.jumpstream           AS "bmr_jumpstream?",
+            bmr.handstream           AS "bmr_handstream?",
+            bmr.stamina              AS "bmr_stamina?",
+            bmr.jackspeed            AS "bmr_jackspeed?",
+            bmr.chordjack            AS "bmr_chordjack?",
+            bmr.technical            AS "bmr_technical?"
+        FROM beatmapset bs
+        INNER JOIN beatmap b ON bs.id = b.beatmapset_id
+        LEFT JOIN rates r ON r.beatmap_id = b.id
+            AND ($2::int4[] IS NULL OR r.centirate = ANY($2))
+            AND ($3::int4 IS NULL OR r.centirate >= $3)
+            AND ($4::int4 IS NULL OR r.centirate <= $4)
+        LEFT JOIN beatmap_rating br ON br.rates_id = r.id
+        LEFT JOIN beatmap_mania_rating bmr ON bmr.rating_id = br.id
+        WHERE bs.id = ANY($1)
+        ORDER BY bs.id ASC, b.id ASC, r.centirate ASC, r.id ASC, br.id ASC
+        "#,
+        beatmapset_ids,
+        centirates.as_deref(),
+        min,
+        max
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut by_id: HashMap<i32, Beatmapset> = group_beatmapset_detail_rows(rows)
+        .into_iter()
+        .filter_map(|set| set.id.map(|id| (id, set)))
+        .collect();
+
+    // Restore the caller's order
+    Ok(beatmapset_ids
+        .iter()
+        .filter_map(|id| by_id.remove(id))
+        .collect())
+}
@@ -0,0 +1,216 @@
+use crate::enums::{GameMode, Known, RankStatus, RatingType};
+use crate::filters::CentirateSelection;
+use crate::models::beatmaps::full::types::{Beatmap, Beatmapset};
+use crate::models::rate::full::query::common::decimal_to_f64;
+use crate::models::rate::{ManiaRating, ModeRating, Rates, Rating};
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::NaiveDateTime;
+use std::collections::HashMap;
+
+/// A rate selection as the nullable query params `(centirates, min, max)`
+/// the detail queries filter their rates join with.
+pub(crate) fn selection_params(
+    rates: &CentirateSelection,
+) -> (Option<Vec<i32>>, Option<i32>, Option<i32>) {
+    match rates {
+        CentirateSelection::Any => (None, None, None),
+        CentirateSelection::Exact(centirate) => (Some(vec![*centirate]), None, None),
+        CentirateSelection::Range { min, max } => (None, *min, *max),
+        CentirateSelection::List(centirates) => (Some(centirates.clone()), None, None),
+    }
+}
+
+/// One joined row beatmapset / beatmap / rate / rating / mania rating.
+///
+/// Everything after the beatmap comes from LEFT JOINs and is optional.
+#[derive(Debug)]
+pub(crate) struct BeatmapsetDetailRow {
+    // beatmapset
+    pub bs_id: i32,
+    pub bs_osu_id: Option<i32>,
+    pub bs_artist: String,
+    pub bs_artist_unicode: Option<String>,
+    pub bs_title: String,
+    pub bs_title_unicode: Option<String>,
+    pub bs_creator: String,
+    pub bs_source: Option<String>,
+    pub bs_tags: Option<Vec<String>>,
+    pub bs_has_video: bool,
+    pub bs_has_storyboard: bool,
+    pub bs_is_explicit: bool,
+    pub bs_is_featured: bool,
+    pub bs_cover_url: Option<String>,
+    pub bs_preview_url: Option<String>,
+    pub bs_osu_file_url: Option<String>,
+    pub bs_osu_status_changed_at: Option<NaiveDateTime>,
+    // beatmap
+    pub b_id: i32,
+    pub b_osu_id: Option<i32>,
+    pub b_beatmapset_id: Option<i32>,
+    pub b_difficulty: String,
+    pub b_count_circles: i32,
+    pub b_count_sliders: i32,
+    pub b_count_spinners: i32,
+    pub b_max_combo: i32,
+    pub b_main_pattern: serde_json::Value,
+    pub b_cs: BigDecimal,
+    pub b_ar: BigDecimal,
+    pub b_od: BigDecimal,
+    pub b_hp: BigDecimal,
+    pub b_mode: Known<GameMode>,
+    pub b_status: Known<RankStatus>,
+    // rates
+    pub r_id: Option<i32>,
+    pub r_osu_hash: Option<String>,
+    pub r_centirate: Option<i32>,
+    pub r_drain_time: Option<i32>,
+    pub r_total_time: Option<i32>,
+    pub r_bpm: Option<BigDecimal>,
+    // rating
+    pub br_id: Option<i32>,
+    pub br_rates_id: Option<i32>,
+    pub br_rating: Option<BigDecimal>,
+    pub br_rating_type: Option<Known<RatingType>>,
+    // mania rating
+    pub bmr_id: Option<i32>,
+    pub bmr_stream: Option<BigDecimal>,
+    pub bmr_jumpstream: Option<BigDecimal>,
+    pub bmr_handstream: Option<BigDecimal>,
+    pub bmr_stamina: Option<BigDecimal>,
+    pub bmr_jackspeed: Option<BigDecimal>,
+    pub bmr_chordjack: Option<BigDecimal>,
+    pub bmr_technical: Option<BigDecimal>,
+}
+
+impl BeatmapsetDetailRow {
+    fn beatmapset(&self) -> Beatmapset {
+        Beatmapset {
+            id: Some(self.bs_id),
+            osu_id: self.bs_osu_id,
+            artist: self.bs_artist.clone(),
+            artist_unicode: self.bs_artist_unicode.clone(),
+            title: self.bs_title.clone(),
+            title_unicode: self.bs_title_unicode.clone(),
+            creator: self.bs_creator.clone(),
+            source: self.bs_source.clone(),
+            tags: self
+                .bs_tags
+                .as_ref()
+                .filter(|v| !v.is_empty())
+                .map(|v| v.join(" ")),
+            has_video: self.bs_has_video,
+            has_storyboard: self.bs_has_storyboard,
+            is_explicit: self.bs_is_explicit,
+            is_featured: self.bs_is_featured,
+            cover_url: self.bs_cover_url.clone(),
+            preview_url: self.bs_preview_url.clone(),
+            osu_file_url: self.bs_osu_file_url.clone(),
+            beatmaps: Vec::new(),
+            osu_status_changed_at: self.bs_osu_status_changed_at,
+        }
+    }
+
+    fn beatmap(&self, mode: GameMode, status: RankStatus) -> Beatmap {
+        Beatmap {
+            id: Some(self.b_id),
+            osu_id: self.b_osu_id,
+            beatmapset_id: self.b_beatmapset_id,
+            difficulty: self.b_difficulty.clone(),
+            count_circles: self.b_count_circles,
+            count_sliders: self.b_count_sliders,
+            count_spinners: self.b_count_spinners,
+            max_combo: self.b_max_combo,
+            cs: self.b_cs.to_f64().unwrap_or_default(),
+            ar: self.b_ar.to_f64().unwrap_or_default(),
+            od: self.b_od.to_f64().unwrap_or_default(),
+            hp: self.b_hp.to_f64().unwrap_or_default(),
+            mode,
+            status,
+            main_pattern: self.b_main_pattern.clone(),
+            rates: Vec::new(),
+        }
+    }
+
+    fn rate(&self, r_id: i32) -> Rates {
+        Rates {
+            id: Some(r_id),
+            osu_hash: self.r_osu_hash.clone(),
+            centirate: self.r_centirate.unwrap_or_default(),
+            drain_time: self.r_drain_time.unwrap_or_default(),
+            total_time: self.r_total_time.unwrap_or_default(),
+            bpm: self
+                .r_bpm
+                .as_ref()
+                .and_then(|v| v.to_f32())
+                .unwrap_or_default(),
+            rating: Vec::new(),
+            estimated: false,
+        }
+    }
+
+    fn rating(&self, br_id: i32) -> Option<Rating> {
+        let rating_type = self.br_rating_type?.0?;
+        let mode_rating = ModeRating::Mania(ManiaRating {
+            id: self.bmr_id,
+            stream: decimal_to_f64(&self.bmr_stream),
+            jumpstream: decimal_to_f64(&self.bmr_jumpstream),
+            handstream: decimal_to_f64(&self.bmr_handstream),
+            stamina: decimal_to_f64(&self.bmr_stamina),
+            jackspeed: decimal_to_f64(&self.bmr_jackspeed),
+            chordjack: decimal_to_f64(&self.bmr_chordjack),
+            technical: decimal_to_f64(&self.bmr_technical),
+        });
+        Some(Rating {
+            id: Some(br_id),
+            rates_id: self.br_rates_id.or(self.r_id),
+            rating: decimal_to_f64(&self.br_rating),
+            rating_type,
+            mode_rating,
+        })
+    }
+}
+
+/// Group joined rows into full beatmapsets, in order of first appearance.
+///
+/// Rows of one beatmapset do not need to be contiguous, but rates and
+/// ratings keep the row order, so sort the query accordingly. Beatmaps of an
+/// unknown mode or status are skipped, as are ratings of an unknown type.
+pub(crate) fn group_beatmapset_detail_rows(rows: Vec<BeatmapsetDetailRow>) -> Vec<Beatmapset> {
+    let mut beatmapsets: Vec<Beatmapset> = Vec::new();
+    let mut set_index: HashMap<i32, usize> = HashMap::new();
+    // beatmap id -> (set index, beatmap index)
+    let mut beatmap_index: HashMap<i32, (usize, usize)> = HashMap::new();
+    // rate id -> index in its beatmap's rates
+    let mut rate_index: HashMap<i32, usize> = HashMap::new();
+
+    for row in rows {
+        let (Known(Some(mode)), Known(Some(status))) = (row.b_mode, row.b_status) else {
+            continue;
+        };
+        let s = *set_index.entry(row.bs_id).or_insert_with(|| {
+            beatmapsets.push(row.beatmapset());
+            beatmapsets.len() - 1
+        });
+        let (s, b) = *beatmap_index.entry(row.b_id).or_insert_with(|| {
+            let beatmaps = &mut beatmapsets[s].beatmaps;
+            beatmaps.push(row.beatmap(mode, status));
+            (s, beatmaps.len() - 1)
+        });
+
+        // No rate at all for this beatmap (LEFT JOIN)
+        let Some(r_id) = row.r_id else {
+            continue;
+        };
+        let rates = &mut beatmapsets[s].beatmaps[b].rates;
+        let r = *rate_index.entry(r_id).or_insert_with(|| {
+            rates.push(row.rate(r_id));
+            rates.len() - 1
+        });
+
+        if let Some(rating) = row.br_id.and_then(|br_id| row.rating(br_id)) {
+            rates[r].rating.push(rating);
+        }
+    }
+
+    beatmapsets
+}
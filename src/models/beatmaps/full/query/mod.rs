@@ -1 +1,5 @@
+pub mod by_checksum;
+pub mod by_ids;
 pub mod by_osu_id;
+pub(crate) mod common;
+pub mod stream;
@@ -0,0 +1,42 @@
+use crate::filters::{Filters, ValidFilters};
+use crate::models::beatmaps::full::types::Beatmapset;
+use crate::models::beatmaps::short::query::stream::{
+    export_filters, fetch_export_ids, open_export_cursor,
+};
+use futures::stream::{self, Stream, TryStreamExt};
+use sqlx::{PgPool, Postgres, Transaction};
+
+use super::by_ids::find_full_by_ids;
+
+/// Full variant of `short::query::stream_with_filters`: the same beatmapsets
+/// in the same order, with every difficulty and its rates in
+/// `filters.rates.centirate` (1.0x when unset).
+///
+/// Walks the same server-side cursor, holding its connection until the
+/// stream ends or is dropped.
+pub fn stream_full_with_filters(
+    pool: &PgPool,
+    filters: ValidFilters,
+) -> impl Stream<Item = Result<Beatmapset, sqlx::Error>> + '_ {
+    let state: (Option<Transaction<'static, Postgres>>, Filters) = (None, export_filters(filters));
+    stream::try_unfold(state, move |(tx, filters)| async move {
+        let mut tx = match tx {
+            Some(tx) => tx,
+            None => open_export_cursor(pool, &filters).await?,
+        };
+        let ids = fetch_export_ids(&mut tx).await?;
+        if ids.is_empty() {
+            tx.commit().await?;
+            return Ok::<_, sqlx::Error>(None);
+        }
+        let rates = filters
+            .rates
+            .as_ref()
+            .and_then(|rates| rates.centirate.clone())
+            .unwrap_or_default();
+        let chunk = find_full_by_ids(pool, &ids, &rates).await?;
+        Ok(Some((chunk, (Some(tx), filters))))
+    })
+    .map_ok(|chunk| stream::iter(chunk.into_iter().map(Ok)))
+    .try_flatten()
+}
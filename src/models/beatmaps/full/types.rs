@@ -1,3 +1,4 @@
+use crate::enums::{GameMode, RankStatus};
 use crate::models::rate;
 use chrono::NaiveDateTime;
 use serde::Serialize;
@@ -39,8 +40,8 @@ pub struct Beatmap {
     pub ar: f64,
     pub od: f64,
     pub hp: f64,
-    pub mode: i32,
-    pub status: String,
+    pub mode: GameMode,
+    pub status: RankStatus,
     pub main_pattern: serde_json::Value,
     pub rates: Vec<rate::Rates>,
 }
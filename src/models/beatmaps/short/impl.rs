@@ -1,3 +1,6 @@
+use crate::enums::{GameMode, ParseEnumError, RatingType};
+use crate::filters::TruncationPolicy;
+use crate::models::beatmaps::short::query::common::truncate_beatmaps;
 use crate::models::beatmaps::short::types::{Beatmap, Beatmapset, Rating};
 use bigdecimal::ToPrimitive;
 use db::models::beatmaps::beatmap::types::BeatmapRow;
@@ -5,27 +8,34 @@ use db::models::beatmaps::beatmapset::types::BeatmapsetRow;
 use db::models::rating::beatmap_rating::types::BeatmapRatingRow;
 
 impl Rating {
-    pub fn from_row(row: BeatmapRatingRow) -> Self {
-        Self {
+    /// Rating rows handed to `from_row` are expected to be 1.0x rates.
+    pub fn from_row(row: BeatmapRatingRow) -> Result<Self, ParseEnumError> {
+        Ok(Self {
             rating: row.rating.to_f64().unwrap(),
-            rating_type: row.rating_type,
-        }
+            rating_type: row.rating_type.parse()?,
+            centirate: 100,
+        })
     }
 }
 
 impl Beatmap {
-    pub fn from_row(row: BeatmapRow, ratings: Vec<BeatmapRatingRow>) -> Self {
-        Self {
+    pub fn from_row(
+        row: BeatmapRow,
+        ratings: Vec<BeatmapRatingRow>,
+    ) -> Result<Self, ParseEnumError> {
+        Ok(Self {
             osu_id: row.osu_id,
             difficulty: row.difficulty,
-            mode: row.mode,
-            status: row.status,
+            mode: GameMode::try_from(row.mode)?,
+            status: row.status.parse()?,
             main_pattern: row.main_pattern,
+            matched: true,
+            centirates: vec![100],
             ratings: ratings
                 .into_iter()
-                .map(|row| Rating::from_row(row))
-                .collect(),
-        }
+                .map(Rating::from_row)
+                .collect::<Result<_, _>>()?,
+        })
     }
 }
 
@@ -34,51 +44,30 @@ impl Beatmapset {
         row: BeatmapsetRow,
         beatmaps: Vec<BeatmapRow>,
         ratings: Vec<BeatmapRatingRow>,
-        rating_type: Option<&str>,
-    ) -> Self {
+        rating_type: Option<RatingType>,
+        policy: &TruncationPolicy,
+    ) -> Result<Self, ParseEnumError> {
         let total_beatmaps = beatmaps.len() as i32;
-        let rating_type = rating_type.unwrap_or("osu");
+        let rating_type = rating_type.unwrap_or_default();
 
         // Créer tous les beatmaps d'abord
         let mut beatmaps_result = Vec::new();
         for beatmap_row in beatmaps {
-            beatmaps_result.push(Beatmap::from_row(beatmap_row, ratings.clone()));
+            beatmaps_result.push(Beatmap::from_row(beatmap_row, ratings.clone())?);
         }
 
-        // Trier par rating croissant
-        beatmaps_result.sort_by(|a, b| {
-            let a_rating = a
-                .ratings
-                .iter()
-                .filter(|r| r.rating_type == rating_type)
-                .map(|r| r.rating)
-                .fold(f64::INFINITY, f64::min);
-
-            let b_rating = b
-                .ratings
-                .iter()
-                .filter(|r| r.rating_type == rating_type)
-                .map(|r| r.rating)
-                .fold(f64::INFINITY, f64::min);
+        // Même sélection que les requêtes de recherche
+        truncate_beatmaps(&mut beatmaps_result, rating_type, policy);
 
-            a_rating
-                .partial_cmp(&b_rating)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-
-        // Prendre les 5 premiers (plus bas rating)
-        if beatmaps_result.len() > 5 {
-            beatmaps_result.truncate(5);
-        }
-
-        Self {
+        Ok(Self {
             osu_id: row.osu_id,
             artist: row.artist,
             title: row.title,
             cover_url: row.cover_url,
             creator: row.creator,
             total_beatmaps,
+            matched_beatmaps: total_beatmaps,
             beatmaps: beatmaps_result,
-        }
+        })
     }
 }
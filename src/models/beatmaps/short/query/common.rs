@@ -1,81 +1,148 @@
-use crate::filters::Filters;
-use crate::models::beatmaps::short::types::Beatmapset;
+use crate::enums::{GameMode, Known, RankStatus, RatingType, Skillset};
+use crate::filters::{
+    BeatmapsetFilter, CentirateSelection, ExclusionFilter, Filters, SearchCursor,
+    SkillsetCombinator, SkillsetsFilter, SortKey, SortOrder, TruncationPolicy,
+};
+use crate::models::beatmaps::short::types::{Beatmap, Beatmapset};
 use bigdecimal::{BigDecimal, ToPrimitive};
 use serde_json::json;
 use sqlx::{Postgres, QueryBuilder};
 use std::collections::HashMap;
 
+use super::search::{push_relevance, push_search_condition};
+
 /// Determine the preferred rating type from filters or fallback to "osu".
-pub fn preferred_rating_type(filters: &Filters) -> &str {
+pub fn preferred_rating_type(filters: &Filters) -> RatingType {
     filters
         .rating
         .as_ref()
-        .and_then(|r| r.rating_type.as_deref())
-        .unwrap_or("osu")
+        .and_then(|r| r.rating_type)
+        .unwrap_or_default()
+}
+
+/// Free-text search term of the filters, if any.
+pub fn search_term(filters: &Filters) -> Option<&str> {
+    filters
+        .beatmap
+        .as_ref()
+        .and_then(|b| b.search_term.as_deref())
 }
 
 /// Compute a comparable score for a beatmap given the preferred rating type.
 /// Fallback to `osu` if the preferred type is unavailable; otherwise return -inf.
-pub fn beatmap_score(
-    beatmap: &crate::models::beatmaps::short::types::Beatmap,
-    preferred_type: &str,
-) -> f64 {
+pub fn beatmap_score(beatmap: &Beatmap, preferred_type: RatingType) -> f64 {
     if let Some(r) = beatmap
         .ratings
         .iter()
         .find(|r| r.rating_type == preferred_type)
     {
         r.rating
-    } else if let Some(r) = beatmap.ratings.iter().find(|r| r.rating_type == "osu") {
+    } else if let Some(r) = beatmap
+        .ratings
+        .iter()
+        .find(|r| r.rating_type == RatingType::Osu)
+    {
         r.rating
     } else {
         f64::NEG_INFINITY
     }
 }
 
-/// Sort beatmaps per set by ascending difficulty according to `preferred_type`,
-/// then keep the 5 easiest plus the single hardest (total up to 6). Also set
-/// `total_beatmaps` to the pre-truncation count for each set.
-pub fn sort_and_limit_beatmaps(beatmapsets: &mut HashMap<i32, Beatmapset>, preferred_type: &str) {
-    for beatmapset in beatmapsets.values_mut() {
-        let original_count = beatmapset.beatmaps.len();
-
-        // Sort easiest first
-        beatmapset.beatmaps.sort_by(|a, b| {
-            beatmap_score(a, preferred_type)
-                .partial_cmp(&beatmap_score(b, preferred_type))
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-
-        if beatmapset.beatmaps.len() > 6 {
-            // Identify the hardest
-            let hardest = beatmapset
-                .beatmaps
-                .iter()
-                .max_by(|a, b| {
-                    beatmap_score(a, preferred_type)
-                        .partial_cmp(&beatmap_score(b, preferred_type))
-                        .unwrap_or(std::cmp::Ordering::Equal)
-                })
-                .cloned();
-
-            // Keep first 5 (easiest) and the hardest
-            let mut kept: Vec<crate::models::beatmaps::short::types::Beatmap> =
-                beatmapset.beatmaps.iter().take(5).cloned().collect();
-            if let Some(h) = hardest {
-                if !kept.iter().any(|bm| bm.osu_id == h.osu_id) {
-                    kept.push(h);
-                }
+/// Sort a set's beatmaps by ascending difficulty according to `preferred_type`
+/// and keep the ones `policy` selects. `All` and `MatchedOnly` keep every
+/// beatmap they are given: which ones that is depends on the query.
+pub fn truncate_beatmaps(
+    beatmaps: &mut Vec<Beatmap>,
+    preferred_type: RatingType,
+    policy: &TruncationPolicy,
+) {
+    let by_score = |a: &Beatmap, b: &Beatmap| {
+        beatmap_score(a, preferred_type)
+            .partial_cmp(&beatmap_score(b, preferred_type))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    };
+
+    match policy {
+        TruncationPolicy::All | TruncationPolicy::MatchedOnly => {}
+        TruncationPolicy::EasiestPlusHardest { n } => {
+            beatmaps.sort_by(by_score);
+            if beatmaps.len() > n + 1 {
+                // Keep the n easiest and the hardest
+                let hardest = beatmaps.pop();
+                beatmaps.truncate(*n);
+                beatmaps.extend(hardest);
             }
-            beatmapset.beatmaps = kept;
         }
+        TruncationPolicy::ClosestToRating { n, target } => {
+            let distance = |b: &Beatmap| (beatmap_score(b, preferred_type) - target).abs();
+            beatmaps.sort_by(|a, b| {
+                distance(a)
+                    .partial_cmp(&distance(b))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            beatmaps.truncate(*n);
+        }
+    }
 
-        beatmapset.total_beatmaps = original_count as i32;
+    // Easiest first
+    beatmaps.sort_by(by_score);
+}
+
+/// Apply `truncate_beatmaps` to every set, and set `matched_beatmaps` to the
+/// pre-truncation count of matched beatmaps.
+pub fn sort_and_limit_beatmaps(
+    beatmapsets: &mut HashMap<i32, Beatmapset>,
+    preferred_type: RatingType,
+    policy: &TruncationPolicy,
+) {
+    for beatmapset in beatmapsets.values_mut() {
+        let matched_count = beatmapset.beatmaps.iter().filter(|b| b.matched).count();
+        truncate_beatmaps(&mut beatmapset.beatmaps, preferred_type, policy);
+        beatmapset.matched_beatmaps = matched_count as i32;
     }
 }
 
-/// Apply filters to a QueryBuilder - used by both find_all_with_filters and find_random_with_filters
+/// Restrict `r` to the requested rates (1.0x when no selection is given).
+pub fn apply_rate_selection<'a>(builder: &mut QueryBuilder<'a, Postgres>, filters: &'a Filters) {
+    let selection = filters
+        .rates
+        .as_ref()
+        .and_then(|r| r.centirate.clone())
+        .unwrap_or_default();
+    match selection {
+        CentirateSelection::Any => {}
+        CentirateSelection::Exact(centirate) => {
+            builder.push(" AND r.centirate = ").push_bind(centirate);
+        }
+        CentirateSelection::Range { min, max } => {
+            if let Some(min) = min {
+                builder.push(" AND r.centirate >= ").push_bind(min);
+            }
+            if let Some(max) = max {
+                builder.push(" AND r.centirate <= ").push_bind(max);
+            }
+        }
+        CentirateSelection::List(centirates) => {
+            builder
+                .push(" AND r.centirate = ANY(")
+                .push_bind(centirates)
+                .push(")");
+        }
+    }
+}
+
+/// Apply filters to a QueryBuilder - used by both find_all_with_filters and find_random_with_filters.
+/// The base query must already contain a `WHERE` clause; every condition is pushed as ` AND ...`.
 pub fn apply_filters<'a>(builder: &mut QueryBuilder<'a, Postgres>, filters: &'a Filters) {
+    apply_rate_selection(builder, filters);
+    if let Some(rates) = filters.rates.as_ref() {
+        if let Some(min) = rates.drain_time_min.as_ref() {
+            builder.push(" AND r.drain_time >= ").push_bind(min);
+        }
+        if let Some(max) = rates.drain_time_max.as_ref() {
+            builder.push(" AND r.drain_time <= ").push_bind(max);
+        }
+    }
     if let Some(rating) = filters.rating.as_ref() {
         if let Some(rt) = rating.rating_type.as_ref() {
             builder.push(" AND br.rating_type = ").push_bind(rt);
@@ -89,15 +156,22 @@ pub fn apply_filters<'a>(builder: &mut QueryBuilder<'a, Postgres>, filters: &'a
     }
     if let Some(beatmap) = filters.beatmap.as_ref() {
         if let Some(term) = beatmap.search_term.as_ref() {
-            let like = format!("%{}%", term);
+            push_search_condition(builder, term);
+        }
+        if let Some(artist) = beatmap.artist.as_ref() {
             builder
-                .push(" AND (bs.artist ILIKE ")
-                .push_bind(like.clone())
-                .push(" OR bs.title ILIKE ")
-                .push_bind(like.clone())
-                .push(" OR bs.creator ILIKE ")
-                .push_bind(like)
-                .push(")");
+                .push(" AND bs.artist ILIKE ")
+                .push_bind(format!("%{}%", artist));
+        }
+        if let Some(title) = beatmap.title.as_ref() {
+            builder
+                .push(" AND bs.title ILIKE ")
+                .push_bind(format!("%{}%", title));
+        }
+        if let Some(creator) = beatmap.creator.as_ref() {
+            builder
+                .push(" AND bs.creator ILIKE ")
+                .push_bind(format!("%{}%", creator));
         }
         if let Some(min) = beatmap.total_time_min.as_ref() {
             builder.push(" AND r.total_time >= ").push_bind(min);
@@ -122,69 +196,381 @@ pub fn apply_filters<'a>(builder: &mut QueryBuilder<'a, Postgres>, filters: &'a
         if let Some(status) = bt.status.as_ref() {
             builder.push(" AND b.status = ").push_bind(status);
         }
+        if let Some(mode) = bt.mode.as_ref() {
+            builder.push(" AND b.mode = ").push_bind(mode);
+        }
+        // Key counts only exist on mania, where CS holds them
+        if bt.keycount_min.is_some() || bt.keycount_max.is_some() {
+            builder.push(" AND b.mode = ").push_bind(GameMode::Mania);
+        }
+        if let Some(min) = bt.keycount_min.as_ref() {
+            builder.push(" AND b.cs >= ").push_bind(min);
+        }
+        if let Some(max) = bt.keycount_max.as_ref() {
+            builder.push(" AND b.cs <= ").push_bind(max);
+        }
+        if let Some(min) = bt.cs_min.as_ref() {
+            builder.push(" AND b.cs >= ").push_bind(min);
+        }
+        if let Some(max) = bt.cs_max.as_ref() {
+            builder.push(" AND b.cs <= ").push_bind(max);
+        }
+        if let Some(min) = bt.ar_min.as_ref() {
+            builder.push(" AND b.ar >= ").push_bind(min);
+        }
+        if let Some(max) = bt.ar_max.as_ref() {
+            builder.push(" AND b.ar <= ").push_bind(max);
+        }
+        if let Some(min) = bt.hp_min.as_ref() {
+            builder.push(" AND b.hp >= ").push_bind(min);
+        }
+        if let Some(max) = bt.hp_max.as_ref() {
+            builder.push(" AND b.hp <= ").push_bind(max);
+        }
+        if let Some(min) = bt.max_combo_min.as_ref() {
+            builder.push(" AND b.max_combo >= ").push_bind(min);
+        }
+        if let Some(max) = bt.max_combo_max.as_ref() {
+            builder.push(" AND b.max_combo <= ").push_bind(max);
+        }
+        if let Some(min) = bt.circles_min.as_ref() {
+            builder.push(" AND b.count_circles >= ").push_bind(min);
+        }
+        if let Some(max) = bt.circles_max.as_ref() {
+            builder.push(" AND b.count_circles <= ").push_bind(max);
+        }
+        if let Some(min) = bt.sliders_min.as_ref() {
+            builder.push(" AND b.count_sliders >= ").push_bind(min);
+        }
+        if let Some(max) = bt.sliders_max.as_ref() {
+            builder.push(" AND b.count_sliders <= ").push_bind(max);
+        }
+    }
+    if let Some(skillsets) = filters.skillsets.as_ref() {
+        apply_skillset_filters(builder, skillsets);
+    }
+    if let Some(bs) = filters.beatmapset.as_ref() {
+        apply_beatmapset_filters(builder, bs);
+    }
+    if let Some(exclude) = filters.exclude.as_ref() {
+        apply_exclusions(builder, exclude);
+    }
+}
+
+fn apply_beatmapset_filters<'a>(
+    builder: &mut QueryBuilder<'a, Postgres>,
+    bs: &'a BeatmapsetFilter,
+) {
+    if let Some(v) = bs.has_video.as_ref() {
+        builder.push(" AND bs.has_video = ").push_bind(v);
+    }
+    if let Some(v) = bs.has_storyboard.as_ref() {
+        builder.push(" AND bs.has_storyboard = ").push_bind(v);
+    }
+    if let Some(v) = bs.is_explicit.as_ref() {
+        builder.push(" AND bs.is_explicit = ").push_bind(v);
+    }
+    if let Some(v) = bs.is_featured.as_ref() {
+        builder.push(" AND bs.is_featured = ").push_bind(v);
+    }
+    if let Some(source) = bs.source.as_ref() {
+        builder
+            .push(" AND LOWER(bs.source) = LOWER(")
+            .push_bind(source)
+            .push(")");
+    }
+    if let Some(source) = bs.source_contains.as_ref() {
+        builder
+            .push(" AND bs.source ILIKE ")
+            .push_bind(format!("%{}%", source));
+    }
+    if !bs.tags.is_empty() {
+        builder.push(" AND bs.tags @> ").push_bind(&bs.tags);
+    }
+    if let Some(tag) = bs.tag_contains.as_ref() {
+        builder
+            .push(" AND EXISTS (SELECT 1 FROM unnest(bs.tags) AS t(tag) WHERE t.tag ILIKE ")
+            .push_bind(format!("%{}%", tag))
+            .push(")");
+    }
+    if let Some(from) = bs.status_changed_from.as_ref() {
+        builder
+            .push(" AND bs.osu_status_changed_at >= ")
+            .push_bind(from);
+    }
+    if let Some(to) = bs.status_changed_to.as_ref() {
+        builder
+            .push(" AND bs.osu_status_changed_at <= ")
+            .push_bind(to);
+    }
+    if let Some(days) = bs.status_changed_within_days.as_ref() {
+        // osu_status_changed_at is stored as UTC without time zone
+        builder
+            .push(" AND bs.osu_status_changed_at >= (NOW() AT TIME ZONE 'UTC') - make_interval(days => ")
+            .push_bind(days)
+            .push(")");
+    }
+}
+
+/// Push the negative constraints. NULL-safe: a NULL column never hides a row.
+/// Creators and tags compare case-insensitively.
+fn apply_exclusions<'a>(builder: &mut QueryBuilder<'a, Postgres>, exclude: &'a ExclusionFilter) {
+    if !exclude.creators.is_empty() {
+        let creators: Vec<String> = exclude.creators.iter().map(|c| c.to_lowercase()).collect();
+        builder
+            .push(" AND NOT COALESCE(LOWER(bs.creator) = ANY(")
+            .push_bind(creators)
+            .push("), FALSE)");
+    }
+    if !exclude.tags.is_empty() {
+        // No rows out of unnest(NULL), so untagged sets stay
+        let tags: Vec<String> = exclude.tags.iter().map(|t| t.to_lowercase()).collect();
+        builder
+            .push(" AND NOT EXISTS (SELECT 1 FROM unnest(bs.tags) AS t(tag) WHERE LOWER(t.tag) = ANY(")
+            .push_bind(tags)
+            .push("))");
+    }
+    if !exclude.patterns.is_empty() {
+        // jsonb `?|`: does any of these strings appear as an array element
+        builder
+            .push(" AND NOT COALESCE(b.main_pattern ?| ")
+            .push_bind(&exclude.patterns)
+            .push(", FALSE)");
+    }
+    if !exclude.beatmapset_ids.is_empty() {
+        builder
+            .push(" AND NOT COALESCE(bs.osu_id = ANY(")
+            .push_bind(&exclude.beatmapset_ids)
+            .push("), FALSE)");
+    }
+}
+
+/// Map a skillset to its `beatmap_mania_rating` column.
+pub fn skillset_column(skillset: Skillset) -> &'static str {
+    match skillset {
+        Skillset::Stream => "bmr.stream",
+        Skillset::Jumpstream => "bmr.jumpstream",
+        Skillset::Handstream => "bmr.handstream",
+        Skillset::Stamina => "bmr.stamina",
+        Skillset::Jackspeed => "bmr.jackspeed",
+        Skillset::Chordjack => "bmr.chordjack",
+        Skillset::Technical => "bmr.technical",
+    }
+}
+
+/// Whether the filters reference `bmr` and therefore need the mania rating join.
+pub fn needs_skillset_join(filters: &Filters) -> bool {
+    filters
+        .skillsets
+        .as_ref()
+        .is_some_and(|s| s.constraints.iter().any(|c| c.pattern_type.is_some()))
+}
+
+/// Compile every skillset constraint into one parenthesized group joined by the
+/// requested combinator, e.g. ` AND ((... stream ...) OR (... chordjack ...))`.
+fn apply_skillset_filters<'a>(
+    builder: &mut QueryBuilder<'a, Postgres>,
+    skillsets: &'a SkillsetsFilter,
+) {
+    let joiner = match skillsets.combinator.unwrap_or_default() {
+        SkillsetCombinator::And => " AND ",
+        SkillsetCombinator::Or => " OR ",
+    };
+    let constraints: Vec<_> = skillsets
+        .constraints
+        .iter()
+        .filter_map(|c| c.pattern_type.as_ref().map(|pt| (pt, c)))
+        .collect();
+    if constraints.is_empty() {
+        return;
+    }
+
+    builder.push(" AND (");
+    for (i, (pattern_type, skill)) in constraints.into_iter().enumerate() {
+        if i > 0 {
+            builder.push(joiner);
+        }
+        // JSONB array contains optimization: b.main_pattern @> '["pattern"]'
+        let arr = json!([pattern_type]);
+        builder.push("(b.main_pattern @> ").push_bind(arr);
+        let column = skillset_column(*pattern_type);
+        if let Some(min) = skill.pattern_min.as_ref() {
+            builder.push(format!(" AND {} >= ", column)).push_bind(min);
+        }
+        if let Some(max) = skill.pattern_max.as_ref() {
+            builder.push(format!(" AND {} <= ", column)).push_bind(max);
+        }
+        builder.push(")");
     }
-    if let Some(skill) = filters.skillset.as_ref() {
-        if let Some(pattern_type) = skill.pattern_type.as_ref() {
-            // JSONB array contains optimization: b.main_pattern @> '["pattern"]'
-            let arr = json!([pattern_type]);
-            builder.push(" AND b.main_pattern @> ").push_bind(arr);
-            if let Some(min) = skill.pattern_min.as_ref() {
-                match pattern_type.as_str() {
-                    "jumpstream" => {
-                        builder.push(" AND bmr.jumpstream >= ").push_bind(min);
-                    }
-                    "stream" => {
-                        builder.push(" AND bmr.stream >= ").push_bind(min);
-                    }
-                    "handstream" => {
-                        builder.push(" AND bmr.handstream >= ").push_bind(min);
-                    }
-                    "stamina" => {
-                        builder.push(" AND bmr.stamina >= ").push_bind(min);
-                    }
-                    "jackspeed" => {
-                        builder.push(" AND bmr.jackspeed >= ").push_bind(min);
-                    }
-                    "chordjack" => {
-                        builder.push(" AND bmr.chordjack >= ").push_bind(min);
-                    }
-                    "technical" => {
-                        builder.push(" AND bmr.technical >= ").push_bind(min);
-                    }
-                    _ => {}
-                }
+    builder.push(")");
+}
+
+/// Push the expression beatmapsets are ordered by. It is evaluated per
+/// beatmapset over its matching rows, so the query must `GROUP BY bs.id`.
+pub fn push_sort_expression<'a>(builder: &mut QueryBuilder<'a, Postgres>, filters: &'a Filters) {
+    let sort = filters.sort.clone().unwrap_or_default();
+    // Ascending looks at the easiest matching difficulty, descending at the hardest
+    let aggregate = match sort.order() {
+        SortOrder::Asc => "MIN",
+        SortOrder::Desc => "MAX",
+    };
+    match sort.key() {
+        SortKey::Id => {
+            builder.push("bs.id");
+        }
+        SortKey::Rating => {
+            builder
+                .push(aggregate)
+                .push("(br.rating) FILTER (WHERE br.rating_type = ")
+                .push_bind(preferred_rating_type(filters))
+                .push(")");
+        }
+        SortKey::Bpm => {
+            builder.push(aggregate).push("(r.bpm)");
+        }
+        SortKey::Length => {
+            builder.push(aggregate).push("(r.total_time)");
+        }
+        SortKey::Od => {
+            builder.push(aggregate).push("(b.od)");
+        }
+        // Beatmapset columns are functionally dependent on bs.id
+        SortKey::StatusChangedAt => {
+            builder.push("bs.osu_status_changed_at");
+        }
+        SortKey::Artist => {
+            builder.push("LOWER(bs.artist)");
+        }
+        SortKey::Title => {
+            builder.push("LOWER(bs.title)");
+        }
+        SortKey::Relevance => match search_term(filters) {
+            Some(term) => {
+                builder.push(aggregate).push("(");
+                push_relevance(builder, term);
+                builder.push(")");
             }
-            if let Some(max) = skill.pattern_max.as_ref() {
-                match pattern_type.as_str() {
-                    "jumpstream" => {
-                        builder.push(" AND bmr.jumpstream <= ").push_bind(max);
-                    }
-                    "stream" => {
-                        builder.push(" AND bmr.stream <= ").push_bind(max);
-                    }
-                    "handstream" => {
-                        builder.push(" AND bmr.handstream <= ").push_bind(max);
-                    }
-                    "stamina" => {
-                        builder.push(" AND bmr.stamina <= ").push_bind(max);
-                    }
-                    "jackspeed" => {
-                        builder.push(" AND bmr.jackspeed <= ").push_bind(max);
-                    }
-                    "chordjack" => {
-                        builder.push(" AND bmr.chordjack <= ").push_bind(max);
-                    }
-                    "technical" => {
-                        builder.push(" AND bmr.technical <= ").push_bind(max);
-                    }
-                    _ => {}
-                }
+            // Nothing to rank against: ties everywhere, ordered by id
+            None => {
+                builder.push("0");
             }
+        },
+        SortKey::Random => {
+            builder
+                .push("md5(bs.id::text || ':' || ")
+                .push_bind(sort.seed())
+                .push("::text)");
+        }
+    }
+}
+
+/// SQL type sort values are compared as, and the value standing in for NULL
+/// so that missing values come last in both directions.
+fn sort_value_type(key: SortKey, order: SortOrder) -> (&'static str, Option<&'static str>) {
+    let last = |asc, desc| match order {
+        SortOrder::Asc => Some(asc),
+        SortOrder::Desc => Some(desc),
+    };
+    match key {
+        SortKey::Id => ("int4", None),
+        SortKey::Rating
+        | SortKey::Bpm
+        | SortKey::Length
+        | SortKey::Od
+        | SortKey::Relevance => {
+            ("float8", last("'Infinity'", "'-Infinity'"))
+        }
+        SortKey::StatusChangedAt => ("timestamp", last("'infinity'", "'-infinity'")),
+        SortKey::Artist | SortKey::Title | SortKey::Random => ("text", None),
+    }
+}
+
+/// Push the sort expression cast to its comparison type with NULLs replaced,
+/// the form used by both `ORDER BY` and keyset cursors.
+pub fn push_sort_key<'a>(builder: &mut QueryBuilder<'a, Postgres>, filters: &'a Filters) {
+    let sort = filters.sort.clone().unwrap_or_default();
+    let (sql_type, null_value) = sort_value_type(sort.key(), sort.order());
+    match null_value {
+        Some(null_value) => {
+            builder.push("COALESCE((");
+            push_sort_expression(builder, filters);
+            builder
+                .push(")::")
+                .push(sql_type)
+                .push(", ")
+                .push(null_value)
+                .push("::")
+                .push(sql_type)
+                .push(")");
         }
+        None => {
+            builder.push("(");
+            push_sort_expression(builder, filters);
+            builder.push(")::").push(sql_type);
+        }
+    }
+}
+
+/// Append ` ORDER BY` for the requested sort, with `bs.id` as tie-breaker so
+/// pages never overlap.
+pub fn apply_sort<'a>(builder: &mut QueryBuilder<'a, Postgres>, filters: &'a Filters) {
+    apply_sort_direction(builder, filters, false);
+}
+
+/// `apply_sort`, optionally walking the order backwards (for `prev_cursor`).
+pub fn apply_sort_direction<'a>(
+    builder: &mut QueryBuilder<'a, Postgres>,
+    filters: &'a Filters,
+    reverse: bool,
+) {
+    let sort = filters.sort.clone().unwrap_or_default();
+    let direction = match (sort.order(), reverse) {
+        (SortOrder::Asc, false) | (SortOrder::Desc, true) => " ASC",
+        (SortOrder::Desc, false) | (SortOrder::Asc, true) => " DESC",
+    };
+    builder.push(" ORDER BY ");
+    if sort.key() != SortKey::Id {
+        push_sort_key(builder, filters);
+        builder.push(direction).push(", ");
     }
+    builder.push("bs.id").push(direction);
 }
 
-/// Group rows by beatmapset - shared logic between find_all_with_filters and find_random_with_filters
+/// Append ` HAVING` keeping the beatmapsets after `cursor` in sort order (or
+/// before it for a `prev_cursor`). Must follow `GROUP BY bs.id`.
+pub fn apply_cursor<'a>(
+    builder: &mut QueryBuilder<'a, Postgres>,
+    filters: &'a Filters,
+    cursor: &SearchCursor,
+) {
+    let sort = filters.sort.clone().unwrap_or_default();
+    let op = match (sort.order(), cursor.before) {
+        (SortOrder::Asc, false) | (SortOrder::Desc, true) => " > ",
+        (SortOrder::Desc, false) | (SortOrder::Asc, true) => " < ",
+    };
+    builder.push(" HAVING ");
+    if sort.key() == SortKey::Id {
+        builder.push("bs.id").push(op).push_bind(cursor.id);
+        return;
+    }
+    let (sql_type, _) = sort_value_type(sort.key(), sort.order());
+    builder.push("(");
+    push_sort_key(builder, filters);
+    builder
+        .push(", bs.id)")
+        .push(op)
+        .push("(CAST(")
+        .push_bind(cursor.value.clone())
+        .push(" AS ")
+        .push(sql_type)
+        .push("), ")
+        .push_bind(cursor.id)
+        .push(")");
+}
+
+/// Group rows by beatmapset - shared logic between find_all_with_filters and find_random_with_filters.
+/// A beatmap is `matched` when any of its rows is; rate and rating columns may
+/// be NULL for a difficulty without ratings at the selected rates.
 pub fn group_beatmapset_rows(
     rows: Vec<sqlx::postgres::PgRow>,
 ) -> Result<HashMap<i32, Beatmapset>, sqlx::Error> {
@@ -195,6 +581,12 @@ pub fn group_beatmapset_rows(
 
     for row in rows {
         let beatmapset_id: i32 = row.try_get("beatmapset_id")?;
+        let (Known(Some(mode)), Known(Some(status))) = (
+            row.try_get::<Known<GameMode>, _>("mode")?,
+            row.try_get::<Known<RankStatus>, _>("status")?,
+        ) else {
+            continue;
+        };
 
         let beatmapset = beatmapsets
             .entry(beatmapset_id)
@@ -204,7 +596,8 @@ pub fn group_beatmapset_rows(
                 title: row.try_get("title").unwrap_or_default(),
                 creator: row.try_get("creator").unwrap_or_default(),
                 cover_url: row.try_get("cover_url").ok(),
-                total_beatmaps: 0,
+                total_beatmaps: row.try_get("total_beatmaps").unwrap_or_default(),
+                matched_beatmaps: 0,
                 beatmaps: Vec::new(),
             });
 
@@ -221,9 +614,11 @@ pub fn group_beatmapset_rows(
                 .push(crate::models::beatmaps::short::types::Beatmap {
                     osu_id: beatmap_osu_id,
                     difficulty: row.try_get("difficulty").unwrap_or_default(),
-                    mode: row.try_get("mode").unwrap_or(0),
-                    status: row.try_get("status").unwrap_or_default(),
+                    mode,
+                    status,
                     main_pattern: row.try_get("main_pattern").unwrap_or(json!({})),
+                    matched: false,
+                    centirates: Vec::new(),
                     ratings: Vec::new(),
                 });
         }
@@ -234,18 +629,162 @@ pub fn group_beatmapset_rows(
             .find(|b| b.osu_id == beatmap_osu_id)
             .unwrap();
 
+        let matched: bool = row.try_get("matched")?;
+        beatmap.matched |= matched;
+
+        // Record which rate matched
+        let Some(centirate) = row.try_get::<Option<i32>, _>("centirate")? else {
+            continue;
+        };
+        if !beatmap.centirates.contains(&centirate) {
+            beatmap.centirates.push(centirate);
+        }
+
         // Add rating
+        let Some(Known(Some(rating_type))) =
+            row.try_get::<Option<Known<RatingType>>, _>("rating_type")?
+        else {
+            continue;
+        };
         let rating_bd: BigDecimal = row
             .try_get("rating")
             .unwrap_or_else(|_| BigDecimal::from(0));
-        let rating_type: String = row.try_get("rating_type").unwrap_or_default();
         beatmap
             .ratings
             .push(crate::models::beatmaps::short::types::Rating {
                 rating: rating_bd.to_f64().unwrap_or(0.0),
                 rating_type,
+                centirate,
             });
     }
 
     Ok(beatmapsets)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filters::SortOptions;
+
+    fn sort_sql(sort: SortOptions) -> String {
+        let filters = Filters {
+            sort: Some(sort),
+            ..Default::default()
+        };
+        let mut builder = QueryBuilder::new("");
+        push_sort_expression(&mut builder, &filters);
+        builder.into_sql()
+    }
+
+    fn filters_sql(filters: &Filters) -> String {
+        let mut builder = QueryBuilder::new("");
+        apply_filters(&mut builder, filters);
+        builder.into_sql()
+    }
+
+    #[test]
+    fn skillsets_are_grouped_under_their_combinator() {
+        let filters = Filters::builder()
+            .centirate(CentirateSelection::Any)
+            .skillset(Skillset::Stream, Some(20.0), None)
+            .skillset(Skillset::Chordjack, None, Some(25.0))
+            .skillset_combinator(SkillsetCombinator::Or)
+            .build();
+        assert_eq!(
+            filters_sql(&filters),
+            concat!(
+                " AND ((b.main_pattern @> $1 AND bmr.stream >= $2)",
+                " OR (b.main_pattern @> $3 AND bmr.chordjack <= $4))",
+            )
+        );
+    }
+
+    #[test]
+    fn keycount_only_matches_mania() {
+        let filters = Filters::builder()
+            .centirate(CentirateSelection::Any)
+            .keycount(Some(4), Some(7))
+            .build();
+        assert_eq!(
+            filters_sql(&filters),
+            " AND b.mode = $1 AND b.cs >= $2 AND b.cs <= $3"
+        );
+    }
+
+    #[test]
+    fn exclusions_keep_rows_with_null_columns() {
+        let filters = Filters::builder()
+            .centirate(CentirateSelection::Any)
+            .exclude_creator("Someone")
+            .exclude_tag("Meme")
+            .exclude_pattern(Skillset::Jackspeed)
+            .exclude_beatmapset(1001)
+            .build();
+        assert_eq!(
+            filters_sql(&filters),
+            concat!(
+                " AND NOT COALESCE(LOWER(bs.creator) = ANY($1), FALSE)",
+                " AND NOT EXISTS (SELECT 1 FROM unnest(bs.tags) AS t(tag) WHERE LOWER(t.tag) = ANY($2))",
+                " AND NOT COALESCE(b.main_pattern ?| $3, FALSE)",
+                " AND NOT COALESCE(bs.osu_id = ANY($4), FALSE)",
+            )
+        );
+    }
+
+    fn cursor_sql(key: SortKey, order: SortOrder, before: bool) -> String {
+        let filters = Filters::builder().sort(key, order).build();
+        let cursor = SearchCursor {
+            key,
+            order,
+            value: Some("180".to_string()),
+            id: 42,
+            before,
+            seed: None,
+            filters: filters.fingerprint(),
+        };
+        let mut builder = QueryBuilder::new("");
+        apply_cursor(&mut builder, &filters, &cursor);
+        builder.into_sql()
+    }
+
+    #[test]
+    fn id_cursor_seeks_on_the_id_alone() {
+        assert_eq!(
+            cursor_sql(SortKey::Id, SortOrder::Asc, false),
+            " HAVING bs.id > $1"
+        );
+        assert_eq!(
+            cursor_sql(SortKey::Id, SortOrder::Asc, true),
+            " HAVING bs.id < $1"
+        );
+    }
+
+    #[test]
+    fn cursor_compares_the_sort_key_and_id_as_a_row() {
+        assert_eq!(
+            cursor_sql(SortKey::Bpm, SortOrder::Desc, false),
+            " HAVING (COALESCE((MAX(r.bpm))::float8, '-Infinity'::float8), bs.id) < (CAST($1 AS float8), $2)"
+        );
+        assert!(cursor_sql(SortKey::Bpm, SortOrder::Desc, true).contains(", bs.id) > (CAST("));
+    }
+
+    #[test]
+    fn random_sort_without_a_seed_does_not_panic() {
+        let sort = SortOptions {
+            key: Some(SortKey::Random),
+            ..Default::default()
+        };
+        assert_eq!(sort_sql(sort), "md5(bs.id::text || ':' || $1::text)");
+    }
+
+    #[test]
+    fn sort_expression_aggregates_in_the_sort_direction() {
+        let sort = |order| SortOptions {
+            key: Some(SortKey::Bpm),
+            order: Some(order),
+            seed: None,
+        };
+        assert!(sort_sql(sort(SortOrder::Asc)).starts_with("MIN("));
+        assert!(sort_sql(sort(SortOrder::Desc)).starts_with("MAX("));
+    }
+}
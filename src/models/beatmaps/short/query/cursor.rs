@@ -0,0 +1,116 @@
+use crate::common::CursorPaginatedResponse;
+use crate::filters::validate::DEFAULT_PER_PAGE;
+use crate::filters::{Filters, SearchCursor, ValidFilters};
+use crate::models::beatmaps::short::types::Beatmapset;
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+
+use super::common::{apply_cursor, apply_filters, apply_sort_direction, push_sort_key};
+use super::find_all::{fetch_beatmapsets, filtered_base_sql};
+
+/// Keyset-paginated variant of `find_all_with_filters`.
+///
+/// Without `filters.cursor` the page starts at `page * per_page`; with one it
+/// resumes right after (or before) the beatmapset the cursor points at, which
+/// stays fast on deep pages and does not shift when sets are added.
+pub async fn find_all_with_cursor(
+    pool: &PgPool,
+    filters: ValidFilters,
+) -> Result<CursorPaginatedResponse<Beatmapset>, sqlx::Error> {
+    let page = find_cursor_page_ids(pool, &filters).await?;
+    let data = fetch_beatmapsets(pool, &filters, &page.beatmapset_ids).await?;
+
+    Ok(CursorPaginatedResponse {
+        message: "Beatmapsets found".to_string(),
+        status: "200".to_string(),
+        data,
+        next_cursor: page.next_cursor,
+        prev_cursor: page.prev_cursor,
+    })
+}
+
+/// Beatmapset ids of one keyset page, with the cursors around it.
+pub(crate) struct CursorPageIds {
+    pub beatmapset_ids: Vec<i32>,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+}
+
+/// Ids half of `find_all_with_cursor`, for already normalized filters.
+pub(crate) async fn find_cursor_page_ids(
+    pool: &PgPool,
+    filters: &Filters,
+) -> Result<CursorPageIds, sqlx::Error> {
+    let per_page = filters.per_page.unwrap_or(DEFAULT_PER_PAGE);
+    let cursor = filters
+        .decoded_cursor()
+        .map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+    let backwards = cursor.as_ref().is_some_and(|c| c.before);
+
+    // The text form of the sort value is what the next cursor carries
+    let mut ids_builder: QueryBuilder<Postgres> = QueryBuilder::new("SELECT bs.id, ");
+    push_sort_key(&mut ids_builder, filters);
+    ids_builder
+        .push("::text AS sort_value")
+        .push(filtered_base_sql(filters));
+
+    apply_filters(&mut ids_builder, filters);
+    ids_builder.push(" GROUP BY bs.id");
+    if let Some(cursor) = cursor.as_ref() {
+        apply_cursor(&mut ids_builder, filters, cursor);
+    }
+    apply_sort_direction(&mut ids_builder, filters, backwards);
+    // One extra row tells whether another page exists in this direction
+    ids_builder.push(" LIMIT ").push_bind(per_page as i64 + 1);
+    if cursor.is_none() {
+        ids_builder.push(" OFFSET ").push_bind(filters.offset());
+    }
+
+    let rows = ids_builder.build().fetch_all(pool).await?;
+    let mut keys: Vec<(i32, Option<String>)> = rows
+        .iter()
+        .map(|row| Ok((row.try_get("id")?, row.try_get("sort_value")?)))
+        .collect::<Result<_, sqlx::Error>>()?;
+
+    let has_more = keys.len() > per_page;
+    keys.truncate(per_page);
+    if backwards {
+        keys.reverse();
+    }
+
+    let sort = filters.sort.clone().unwrap_or_default();
+    let fingerprint = filters.fingerprint();
+    let make_cursor = |(id, value): &(i32, Option<String>), before: bool| {
+        SearchCursor {
+            key: sort.key(),
+            order: sort.order(),
+            value: value.clone(),
+            id: *id,
+            before,
+            seed: sort.seed,
+            filters: fingerprint,
+        }
+        .encode()
+    };
+    // Walking backwards we came from a next page; walking forwards we came
+    // from a previous one as soon as we are past the first row
+    let has_next = if backwards { true } else { has_more };
+    let has_prev = if backwards {
+        has_more
+    } else {
+        cursor.is_some() || filters.page.unwrap_or(0) > 0
+    };
+    let next_cursor = keys
+        .last()
+        .filter(|_| has_next)
+        .map(|k| make_cursor(k, false));
+    let prev_cursor = keys
+        .first()
+        .filter(|_| has_prev)
+        .map(|k| make_cursor(k, true));
+
+    Ok(CursorPageIds {
+        beatmapset_ids: keys.into_iter().map(|(id, _)| id).collect(),
+        next_cursor,
+        prev_cursor,
+    })
+}
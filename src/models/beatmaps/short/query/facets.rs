@@ -0,0 +1,133 @@
+use crate::enums::{GameMode, RankStatus, RatingType};
+use crate::filters::ValidFilters;
+use crate::models::beatmaps::short::types::{FacetCount, HistogramBucket, SearchFacets};
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+use std::cmp::Reverse;
+
+use super::common::{apply_filters, preferred_rating_type};
+use super::find_all::filtered_base_sql;
+
+/// Width of a rating histogram bucket.
+pub const RATING_BUCKET_WIDTH: f64 = 1.0;
+/// Width of a BPM histogram bucket.
+pub const BPM_BUCKET_WIDTH: f64 = 10.0;
+
+/// Facet counts for the sidebar of a search, in a single query.
+///
+/// The matching rows are computed once with `apply_filters`, then counted per
+/// facet: statuses, patterns and key counts count beatmapsets, the rating and
+/// BPM histograms count difficulties.
+pub async fn find_facets_with_filters(
+    pool: &PgPool,
+    filters: &ValidFilters,
+) -> Result<SearchFacets, sqlx::Error> {
+    let rating_type = preferred_rating_type(filters);
+
+    let rows = facets_query(filters, rating_type)
+        .build()
+        .fetch_all(pool)
+        .await?;
+
+    let mut facets = SearchFacets {
+        statuses: Vec::new(),
+        patterns: Vec::new(),
+        keycounts: Vec::new(),
+        rating_type,
+        ratings: Vec::new(),
+        bpms: Vec::new(),
+    };
+    let bucket = |value: &str, width: f64| -> Result<HistogramBucket, sqlx::Error> {
+        let index: i64 = value
+            .parse()
+            .map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+        Ok(HistogramBucket {
+            min: index as f64 * width,
+            max: (index + 1) as f64 * width,
+            count: 0,
+        })
+    };
+
+    for row in rows {
+        let facet: String = row.try_get("facet")?;
+        // NULL status, key count or bucket: nothing to count it under
+        let Some(value) = row.try_get::<Option<String>, _>("value")? else {
+            continue;
+        };
+        let count: i64 = row.try_get("count")?;
+        match facet.as_str() {
+            "status" => match value.parse::<RankStatus>() {
+                Ok(value) => facets.statuses.push(FacetCount { value, count }),
+                Err(e) => log::warn!("skipping status facet: {}", e),
+            },
+            "pattern" => facets.patterns.push(FacetCount { value, count }),
+            "keycount" => facets.keycounts.push(FacetCount {
+                value: value
+                    .parse()
+                    .map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+                count,
+            }),
+            "rating" => facets.ratings.push(HistogramBucket {
+                count,
+                ..bucket(&value, RATING_BUCKET_WIDTH)?
+            }),
+            _ => facets.bpms.push(HistogramBucket {
+                count,
+                ..bucket(&value, BPM_BUCKET_WIDTH)?
+            }),
+        }
+    }
+
+    // Most common values first, histograms in bucket order
+    facets.statuses.sort_by_key(|s| Reverse(s.count));
+    facets
+        .patterns
+        .sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    facets.keycounts.sort_by_key(|k| k.value);
+    facets.ratings.sort_by(|a, b| a.min.total_cmp(&b.min));
+    facets.bpms.sort_by(|a, b| a.min.total_cmp(&b.min));
+
+    Ok(facets)
+}
+
+/// The facet query: the filtered rows once, then one `UNION ALL` branch per
+/// facet, yielding `(facet, value, count)` rows.
+fn facets_query<'a>(
+    filters: &'a ValidFilters,
+    rating_type: RatingType,
+) -> QueryBuilder<'a, Postgres> {
+    let mut builder: QueryBuilder<'a, Postgres> = QueryBuilder::new(
+        "WITH matched AS (\n            SELECT bs.id AS set_id, b.id AS beatmap_id, b.status, b.main_pattern,\n                b.mode, b.cs, br.rating, br.rating_type, r.bpm",
+    );
+    builder.push(filtered_base_sql(filters));
+    apply_filters(&mut builder, filters);
+    builder.push(
+        ")\n        SELECT 'status' AS facet, status AS value, COUNT(DISTINCT set_id) AS count\n        FROM matched GROUP BY status\n        UNION ALL\n        SELECT 'pattern', p.pattern, COUNT(DISTINCT set_id)\n        FROM matched\n        CROSS JOIN LATERAL jsonb_array_elements_text(\n            CASE WHEN jsonb_typeof(main_pattern) = 'array' THEN main_pattern ELSE '[]'::jsonb END\n        ) AS p(pattern)\n        GROUP BY p.pattern\n        UNION ALL\n        SELECT 'keycount', ROUND(cs)::int::text, COUNT(DISTINCT set_id)\n        FROM matched WHERE mode = ",
+    );
+    builder
+        .push_bind(GameMode::Mania)
+        .push(
+            " GROUP BY ROUND(cs)\n        UNION ALL\n        SELECT 'rating', FLOOR(rating / ",
+        )
+        .push_bind(RATING_BUCKET_WIDTH)
+        .push(")::bigint::text, COUNT(DISTINCT beatmap_id)\n        FROM matched WHERE rating_type = ")
+        .push_bind(rating_type)
+        .push(" GROUP BY 2\n        UNION ALL\n        SELECT 'bpm', FLOOR(bpm / ")
+        .push_bind(BPM_BUCKET_WIDTH)
+        .push(")::bigint::text, COUNT(DISTINCT beatmap_id)\n        FROM matched GROUP BY 2");
+    builder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filters::Filters;
+
+    #[test]
+    fn binds_the_mania_mode_for_key_counts() {
+        let filters = Filters::default().validated().unwrap();
+        let sql = facets_query(&filters, RatingType::Etterna).into_sql();
+
+        assert!(sql.contains("FROM matched WHERE mode = $2 GROUP BY ROUND(cs)"));
+        assert!(!sql.contains("mode = 3"));
+    }
+}
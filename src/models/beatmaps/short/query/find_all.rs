@@ -1,125 +1,152 @@
-use crate::filters::Filters;
+use crate::common::{PaginatedResponse, Pagination};
+use crate::filters::validate::DEFAULT_PER_PAGE;
+use crate::filters::{Filters, TruncationPolicy, ValidFilters};
 use crate::models::beatmaps::short::types::Beatmapset;
 use sqlx::{PgPool, Postgres, QueryBuilder, Row};
 
 use super::common::{
-    apply_filters, group_beatmapset_rows, preferred_rating_type, sort_and_limit_beatmaps,
+    apply_filters, apply_rate_selection, apply_sort, group_beatmapset_rows, needs_skillset_join,
+    preferred_rating_type, sort_and_limit_beatmaps,
 };
 
 pub async fn find_all_with_filters(
     pool: &PgPool,
-    filters: Filters,
+    filters: ValidFilters,
 ) -> Result<Vec<Beatmapset>, sqlx::Error> {
-    let page = filters.page.unwrap_or(0);
-    let per_page = filters.per_page.unwrap_or(9);
-    let offset = page * per_page;
+    let per_page = filters.per_page.unwrap_or(DEFAULT_PER_PAGE);
 
-    // Determine which joins are actually needed for filters
-    let needs_rating = true; // always join br to satisfy filters that reference it
-    let needs_skill = filters
-        .skillset
-        .as_ref()
-        .and_then(|s| s.pattern_type.as_ref())
-        .is_some();
-
-    // Phase 1: fetch paginated beatmapset ids using DISTINCT over filtered base
-    let mut ids_sql = String::from(
-        "SELECT DISTINCT bs.id FROM beatmapset bs\n            INNER JOIN beatmap b ON bs.id = b.beatmapset_id\n            INNER JOIN rates r ON b.id = r.beatmap_id",
-    );
-    // Always join rating table to avoid missing FROM when filters add conditions on br
-    ids_sql.push_str("\n            INNER JOIN beatmap_rating br ON r.id = br.rates_id");
-    if needs_skill {
-        // requires br join too since bmr is linked via rating
-        if !needs_rating {
-            ids_sql.push_str("\n            INNER JOIN beatmap_rating br ON r.id = br.rates_id");
-        }
-        ids_sql.push_str("\n            LEFT JOIN beatmap_mania_rating bmr ON br.id = bmr.rating_id");
-    }
-    ids_sql.push_str("\n            WHERE r.centirate = 100");
+    // Phase 1: fetch paginated beatmapset ids, grouped so the sort can aggregate
+    let mut ids_sql = String::from("SELECT bs.id");
+    ids_sql.push_str(&filtered_base_sql(&filters));
 
     let mut ids_builder: QueryBuilder<Postgres> = QueryBuilder::new(ids_sql);
 
     // Apply filters using the common function
     apply_filters(&mut ids_builder, &filters);
 
+    ids_builder.push(" GROUP BY bs.id");
+    apply_sort(&mut ids_builder, &filters);
     ids_builder
-        .push(" ORDER BY bs.id LIMIT ")
+        .push(" LIMIT ")
         .push_bind(per_page as i64)
         .push(" OFFSET ")
-        .push_bind(offset as i64);
+        .push_bind(filters.offset());
 
     let beatmapset_ids: Vec<i32> = ids_builder.build_query_scalar().fetch_all(pool).await?;
 
+    fetch_beatmapsets(pool, &filters, &beatmapset_ids).await
+}
+
+/// `FROM ... WHERE TRUE` over the joins the filters need, for the ids queries.
+pub(super) fn filtered_base_sql(filters: &Filters) -> String {
+    // Determine which joins are actually needed for filters
+    let needs_rating = true; // always join br to satisfy filters that reference it
+    let needs_skill = needs_skillset_join(filters);
+
+    let mut sql = String::from(
+        " FROM beatmapset bs\n            INNER JOIN beatmap b ON bs.id = b.beatmapset_id\n            INNER JOIN rates r ON b.id = r.beatmap_id",
+    );
+    // Always join rating table to avoid missing FROM when filters add conditions on br
+    sql.push_str("\n            INNER JOIN beatmap_rating br ON r.id = br.rates_id");
+    if needs_skill {
+        // requires br join too since bmr is linked via rating
+        if !needs_rating {
+            sql.push_str("\n            INNER JOIN beatmap_rating br ON r.id = br.rates_id");
+        }
+        sql.push_str("\n            LEFT JOIN beatmap_mania_rating bmr ON br.id = bmr.rating_id");
+    }
+    sql.push_str("\n            WHERE TRUE");
+    sql
+}
+
+/// Phase 2: fetch detailed rows for the selected beatmapsets, returned in the
+/// order of `beatmapset_ids`.
+///
+/// With `TruncationPolicy::All` every difficulty of the sets is fetched, at
+/// the searched rates, and the filters only compute each row's `matched` flag.
+pub(super) async fn fetch_beatmapsets<'a>(
+    pool: &PgPool,
+    filters: &'a Filters,
+    beatmapset_ids: &'a [i32],
+) -> Result<Vec<Beatmapset>, sqlx::Error> {
     if beatmapset_ids.is_empty() {
         return Ok(Vec::new());
     }
 
-    // Phase 2: fetch detailed rows for selected beatmapset ids
+    let needs_skill = needs_skillset_join(filters);
+    let policy = filters.truncation.clone().unwrap_or_default();
+    let whole_sets = policy == TruncationPolicy::All;
+
     let mut detail_sql = String::from(
-        "SELECT\n            bs.id as beatmapset_id,\n            bs.osu_id as beatmapset_osu_id,\n            bs.artist,\n            bs.title,\n            bs.creator,\n            bs.cover_url,\n            b.id as beatmap_id,\n            b.osu_id as beatmap_osu_id,\n            b.difficulty,\n            b.mode,\n            b.status,\n            b.main_pattern,\n            b.od,\n            r.drain_time",
+        "SELECT\n            bs.id as beatmapset_id,\n            bs.osu_id as beatmapset_osu_id,\n            bs.artist,\n            bs.title,\n            bs.creator,\n            bs.cover_url,\n            (SELECT COUNT(*) FROM beatmap bc WHERE bc.beatmapset_id = bs.id)::int4 as total_beatmaps,\n            b.id as beatmap_id,\n            b.osu_id as beatmap_osu_id,\n            b.difficulty,\n            b.mode,\n            b.status,\n            b.main_pattern,\n            b.od,\n            r.drain_time,\n            r.centirate",
+    );
+    detail_sql.push_str(
+        ",\n            br.id as rating_id,\n            br.rating,\n            br.rating_type",
     );
-    detail_sql.push_str(",\n            br.id as rating_id,\n            br.rating,\n            br.rating_type");
     if needs_skill {
         detail_sql.push_str(",\n            bmr.stream as mania_stream,\n            bmr.jumpstream as mania_jumpstream,\n            bmr.handstream as mania_handstream,\n            bmr.stamina as mania_stamina,\n            bmr.jackspeed as mania_jackspeed,\n            bmr.chordjack as mania_chordjack,\n            bmr.technical as mania_technical");
     } else {
         detail_sql.push_str(",\n            NULL::numeric as mania_stream,\n            NULL::numeric as mania_jumpstream,\n            NULL::numeric as mania_handstream,\n            NULL::numeric as mania_stamina,\n            NULL::numeric as mania_jackspeed,\n            NULL::numeric as mania_chordjack,\n            NULL::numeric as mania_technical");
     }
-    detail_sql.push_str(
-        "\n        FROM beatmapset bs\n        INNER JOIN beatmap b ON bs.id = b.beatmapset_id\n        INNER JOIN rates r ON b.id = r.beatmap_id",
-    );
-    detail_sql.push_str("\n        INNER JOIN beatmap_rating br ON r.id = br.rates_id");
-    if needs_skill {
-        if !needs_rating {
-            detail_sql.push_str("\n        INNER JOIN beatmap_rating br ON r.id = br.rates_id");
-        }
-        detail_sql.push_str("\n        LEFT JOIN beatmap_mania_rating bmr ON br.id = bmr.rating_id");
-    }
-    detail_sql.push_str("\n        WHERE r.centirate = 100");
 
     let mut builder: QueryBuilder<Postgres> = QueryBuilder::new(detail_sql);
 
-    // Re-apply filters using the common function
-    apply_filters(&mut builder, &filters);
+    if whole_sets {
+        // Same conditions as the search, evaluated per row instead of filtering
+        builder.push(",\n            COALESCE((TRUE");
+        apply_filters(&mut builder, filters);
+        builder.push("), FALSE) as matched");
+
+        // Every difficulty, with its ratings at the searched rates when it has some
+        builder.push(
+            "\n        FROM beatmapset bs\n        INNER JOIN beatmap b ON bs.id = b.beatmapset_id\n        LEFT JOIN rates r ON b.id = r.beatmap_id",
+        );
+        apply_rate_selection(&mut builder, filters);
+        builder.push("\n        LEFT JOIN beatmap_rating br ON r.id = br.rates_id");
+        if needs_skill {
+            builder.push("\n        LEFT JOIN beatmap_mania_rating bmr ON br.id = bmr.rating_id");
+        }
+        builder.push("\n        WHERE TRUE");
+    } else {
+        builder.push(",\n            TRUE as matched");
+        builder.push(
+            "\n        FROM beatmapset bs\n        INNER JOIN beatmap b ON bs.id = b.beatmapset_id\n        INNER JOIN rates r ON b.id = r.beatmap_id\n        INNER JOIN beatmap_rating br ON r.id = br.rates_id",
+        );
+        if needs_skill {
+            builder.push("\n        LEFT JOIN beatmap_mania_rating bmr ON br.id = bmr.rating_id");
+        }
+        builder.push("\n        WHERE TRUE");
+
+        // Re-apply filters using the common function
+        apply_filters(&mut builder, filters);
+    }
 
     // Constrain to selected beatmapsets
     builder
         .push(" AND bs.id = ANY(")
-        .push_bind(&beatmapset_ids)
+        .push_bind(beatmapset_ids)
         .push(")");
-    builder.push(" ORDER BY bs.id, b.id, br.id");
+    builder.push(" ORDER BY bs.id, b.id, r.centirate, br.id");
 
     let rows = builder.build().fetch_all(pool).await?;
 
     // Group by beatmapset using the common function
     let mut beatmapsets = group_beatmapset_rows(rows)?;
 
-    let preferred_type = preferred_rating_type(&filters);
-    sort_and_limit_beatmaps(&mut beatmapsets, preferred_type);
+    let preferred_type = preferred_rating_type(filters);
+    sort_and_limit_beatmaps(&mut beatmapsets, preferred_type, &policy);
 
-    Ok(beatmapsets.into_values().collect())
+    // Restore the order of the ids query
+    Ok(beatmapset_ids
+        .iter()
+        .filter_map(|id| beatmapsets.remove(id))
+        .collect())
 }
 
-pub async fn count_with_filters(pool: &PgPool, filters: &Filters) -> Result<i64, sqlx::Error> {
-    let needs_rating = filters.rating.is_some();
-    let needs_skill = filters
-        .skillset
-        .as_ref()
-        .and_then(|s| s.pattern_type.as_ref())
-        .is_some();
-
-    let mut count_sql = String::from(
-        "SELECT COUNT(DISTINCT bs.id) AS total\n        FROM beatmapset bs\n        INNER JOIN beatmap b ON bs.id = b.beatmapset_id\n        INNER JOIN rates r ON b.id = r.beatmap_id",
-    );
-    if needs_rating {
-        count_sql.push_str("\n        INNER JOIN beatmap_rating br ON r.id = br.rates_id");
-    }
-    if needs_skill {
-        if !needs_rating {
-            count_sql.push_str("\n        INNER JOIN beatmap_rating br ON r.id = br.rates_id");
-        }
-        count_sql.push_str("\n        LEFT JOIN beatmap_mania_rating bmr ON br.id = bmr.rating_id");
-    }
-    count_sql.push_str("\n        WHERE r.centirate = 100");
+pub async fn count_with_filters(pool: &PgPool, filters: &ValidFilters) -> Result<i64, sqlx::Error> {
+    // Same base as find_all_with_filters, so sets without ratings are never counted
+    let mut count_sql = String::from("SELECT COUNT(DISTINCT bs.id) AS total");
+    count_sql.push_str(&filtered_base_sql(filters));
 
     let mut builder: QueryBuilder<Postgres> = QueryBuilder::new(count_sql);
 
@@ -131,3 +158,57 @@ pub async fn count_with_filters(pool: &PgPool, filters: &Filters) -> Result<i64,
     let total: i64 = row.try_get("total")?;
     Ok(total)
 }
+
+/// `find_all_with_filters` and `count_with_filters` in one call.
+///
+/// The page and the total come from the same filtered base: the total is a
+/// window count over the grouped ids, with a separate count only when the
+/// requested page is past the end.
+pub async fn find_paginated_with_filters(
+    pool: &PgPool,
+    filters: ValidFilters,
+) -> Result<PaginatedResponse<Beatmapset>, sqlx::Error> {
+    let page = filters.page.unwrap_or(0);
+    let per_page = filters.per_page.unwrap_or(DEFAULT_PER_PAGE);
+
+    let mut ids_sql = String::from("SELECT bs.id, COUNT(*) OVER () AS total");
+    ids_sql.push_str(&filtered_base_sql(&filters));
+
+    let mut ids_builder: QueryBuilder<Postgres> = QueryBuilder::new(ids_sql);
+    apply_filters(&mut ids_builder, &filters);
+    ids_builder.push(" GROUP BY bs.id");
+    apply_sort(&mut ids_builder, &filters);
+    ids_builder
+        .push(" LIMIT ")
+        .push_bind(per_page as i64)
+        .push(" OFFSET ")
+        .push_bind(filters.offset());
+
+    let rows = ids_builder.build().fetch_all(pool).await?;
+    let beatmapset_ids: Vec<i32> = rows
+        .iter()
+        .map(|row| row.try_get("id"))
+        .collect::<Result<_, _>>()?;
+    let total = match rows.first() {
+        Some(row) => row.try_get::<i64, _>("total")?,
+        None if page == 0 => 0,
+        None => count_with_filters(pool, &filters).await?,
+    };
+
+    let data = fetch_beatmapsets(pool, &filters, &beatmapset_ids).await?;
+
+    let total = total.max(0) as u64;
+    let total_pages = total.div_ceil(per_page as u64);
+    Ok(PaginatedResponse {
+        message: "Beatmapsets found".to_string(),
+        status: "200".to_string(),
+        data,
+        pagination: Pagination {
+            page: page as u32,
+            per_page: per_page as u32,
+            total,
+            total_pages: total_pages as u32,
+            has_next: (page as u64 + 1) < total_pages,
+        },
+    })
+}
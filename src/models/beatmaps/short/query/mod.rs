@@ -1,7 +1,15 @@
 pub mod common;
+pub mod cursor;
+pub mod facets;
 pub mod find_all;
 pub mod random;
+pub mod search;
+pub mod stream;
 
+pub use cursor::find_all_with_cursor;
+pub use facets::find_facets_with_filters;
 pub use find_all::count_with_filters;
 pub use find_all::find_all_with_filters;
-pub use random::find_random_with_filters;
+pub use find_all::find_paginated_with_filters;
+pub use random::{find_random_with_filters, find_random_with_options, RandomOptions};
+pub use stream::{stream_with_filters, EXPORT_CHUNK_SIZE};
@@ -1,55 +1,225 @@
-use crate::filters::Filters;
+use crate::filters::validate::{DEFAULT_PER_PAGE, MAX_PER_PAGE};
+use crate::filters::{Filters, ValidFilters};
 use crate::models::beatmaps::short::types::Beatmapset;
+use serde::Deserialize;
 use sqlx::{PgPool, Postgres, QueryBuilder};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use utoipa::ToSchema;
 
-use super::common::{
-    apply_filters, group_beatmapset_rows, preferred_rating_type, sort_and_limit_beatmaps,
-};
+use super::common::{apply_filters, needs_skillset_join, preferred_rating_type};
+use super::find_all::{fetch_beatmapsets, filtered_base_sql};
 
-/// Find random beatmapsets with filters - optimized for speed by using a single query with RANDOM()
+/// Options of a random selection
+#[derive(Deserialize, Debug, Clone, Default, ToSchema)]
+pub struct RandomOptions {
+    /// Number of beatmapsets to return, defaults to 9
+    pub count: Option<usize>,
+    /// Same seed and filters give the same selection
+    pub seed: Option<u64>,
+    /// osu! beatmapset ids to leave out, e.g. the ones the user just saw
+    #[serde(default)]
+    pub exclude_beatmapset_ids: Vec<i32>,
+    /// Favour beatmapsets with a difficulty close to this rating
+    /// (preferred rating type of the filters)
+    pub target_rating: Option<f64>,
+    /// How far from `target_rating` a set may be and still be likely, defaults to 1.0
+    pub rating_spread: Option<f64>,
+}
+
+/// Find random beatmapsets with filters, 9 at a time and unseeded.
 pub async fn find_random_with_filters(
     pool: &PgPool,
-    filters: Filters,
+    filters: ValidFilters,
 ) -> Result<Vec<Beatmapset>, sqlx::Error> {
-    // Phase 1: fetch 9 random beatmapset ids using a subquery to handle DISTINCT + ORDER BY RANDOM()
-    // PostgreSQL doesn't allow ORDER BY RANDOM() directly with SELECT DISTINCT
-    let mut ids_builder: QueryBuilder<Postgres> = QueryBuilder::new(
-        "SELECT id FROM (SELECT DISTINCT bs.id FROM beatmapset bs \n            INNER JOIN beatmap b ON bs.id = b.beatmapset_id\n            INNER JOIN rates r ON b.id = r.beatmap_id\n            INNER JOIN beatmap_rating br ON r.id = br.rates_id\n            LEFT JOIN beatmap_mania_rating bmr ON br.id = bmr.rating_id\n            WHERE r.centirate = 100",
-    );
+    find_random_with_options(pool, filters, RandomOptions::default()).await
+}
 
-    // Apply filters using the common function
-    apply_filters(&mut ids_builder, &filters);
+/// Find random beatmapsets with filters.
+///
+/// Sampling happens in SQL, without `ORDER BY RANDOM()` over every match:
+///
+/// * unweighted, each matching set gets the key `md5(id || seed)` and the
+///   `count` lowest keys are kept (a top-N sort, nothing is materialized):
+///   a uniform sample, independent from one seed to the next.
+/// * with `target_rating`, only matching difficulties within `WEIGHT_WINDOW`
+///   spreads of the target are looked at, and the database keeps the `count`
+///   best Efraimidis–Spirakis keys (`ln(u) / weight`, weight from the
+///   distance to the target). Missing sets are filled in unweighted.
+///
+/// The same seed and filters always give the same selection.
+pub async fn find_random_with_options(
+    pool: &PgPool,
+    filters: ValidFilters,
+    options: RandomOptions,
+) -> Result<Vec<Beatmapset>, sqlx::Error> {
+    let count = options
+        .count
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+    let seed = options
+        .seed
+        .unwrap_or_else(|| RandomState::new().build_hasher().finish());
+
+    let mut beatmapset_ids = match options.target_rating {
+        Some(target) => sample_weighted_ids(pool, &filters, &options, target, seed, count).await?,
+        None => Vec::new(),
+    };
+    if beatmapset_ids.len() < count {
+        let missing = count - beatmapset_ids.len();
+        let more = sample_ids(pool, &filters, &options, &beatmapset_ids, seed, missing).await?;
+        beatmapset_ids.extend(more);
+    }
 
-    ids_builder.push(") AS filtered_ids ORDER BY RANDOM() LIMIT 9");
+    // Phase 2: detailed rows, in sampling order
+    fetch_beatmapsets(pool, &filters, &beatmapset_ids).await
+}
 
-    let beatmapset_ids: Vec<i32> = ids_builder.build_query_scalar().fetch_all(pool).await?;
+/// Seeded key of a beatmapset, `md5(id || ':' || seed)`, pushed with the
+/// seed bound.
+fn push_sample_key<'a>(builder: &mut QueryBuilder<'a, Postgres>, seed: u64) {
+    builder
+        .push("md5(bs.id::text || ':' || ")
+        .push_bind(seed.to_string())
+        .push(")");
+}
 
-    if beatmapset_ids.is_empty() {
-        return Ok(Vec::new());
-    }
+/// Difficulties further than this many `rating_spread`s from `target_rating`
+/// weigh less than e^-8 and are not considered.
+const WEIGHT_WINDOW: f64 = 4.0;
 
-    // Phase 2: fetch detailed rows for selected beatmapset ids (same as find_all_with_filters)
-    let mut builder: QueryBuilder<Postgres> = QueryBuilder::new(
-        "SELECT\n            bs.id as beatmapset_id,\n            bs.osu_id as beatmapset_osu_id,\n            bs.artist,\n            bs.title,\n            bs.creator,\n            bs.cover_url,\n            b.id as beatmap_id,\n            b.osu_id as beatmap_osu_id,\n            b.difficulty,\n            b.mode,\n            b.status,\n            b.main_pattern,\n            b.od,\n            r.drain_time,\n            br.id as rating_id,\n            br.rating,\n            br.rating_type,\n            bmr.stream as mania_stream,\n            bmr.jumpstream as mania_jumpstream,\n            bmr.handstream as mania_handstream,\n            bmr.stamina as mania_stamina,\n            bmr.jackspeed as mania_jackspeed,\n            bmr.chordjack as mania_chordjack,\n            bmr.technical as mania_technical\n        FROM beatmapset bs\n        INNER JOIN beatmap b ON bs.id = b.beatmapset_id\n        INNER JOIN rates r ON b.id = r.beatmap_id\n        INNER JOIN beatmap_rating br ON r.id = br.rates_id\n        LEFT JOIN beatmap_mania_rating bmr ON br.id = bmr.rating_id\n        WHERE r.centirate = 100",
+/// `FROM ... WHERE` of an unweighted sample: filters checked per set with
+/// `EXISTS`, so nothing is grouped, plus the exclusions of the options and
+/// `skip`.
+fn push_sample_conditions<'a>(
+    builder: &mut QueryBuilder<'a, Postgres>,
+    filters: &'a Filters,
+    options: &'a RandomOptions,
+    skip: &'a [i32],
+) {
+    builder.push(
+        " FROM beatmapset bs WHERE EXISTS (SELECT 1 FROM beatmap b\n            INNER JOIN rates r ON b.id = r.beatmap_id\n            INNER JOIN beatmap_rating br ON r.id = br.rates_id",
     );
+    if needs_skillset_join(filters) {
+        builder.push("\n            LEFT JOIN beatmap_mania_rating bmr ON br.id = bmr.rating_id");
+    }
+    builder.push("\n            WHERE b.beatmapset_id = bs.id");
+    apply_filters(builder, filters);
+    builder.push(")");
+    if !options.exclude_beatmapset_ids.is_empty() {
+        builder
+            .push(" AND NOT COALESCE(bs.osu_id = ANY(")
+            .push_bind(&options.exclude_beatmapset_ids)
+            .push("), FALSE)");
+    }
+    if !skip.is_empty() {
+        builder
+            .push(" AND NOT (bs.id = ANY(")
+            .push_bind(skip)
+            .push("))");
+    }
+}
 
-    // Re-apply filters using the common function
-    apply_filters(&mut builder, &filters);
+/// Unweighted sample: the `count` matching sets with the lowest seeded keys.
+async fn sample_ids(
+    pool: &PgPool,
+    filters: &Filters,
+    options: &RandomOptions,
+    skip: &[i32],
+    seed: u64,
+    count: usize,
+) -> Result<Vec<i32>, sqlx::Error> {
+    sample_query(filters, options, skip, seed, count)
+        .build_query_scalar()
+        .fetch_all(pool)
+        .await
+}
 
-    // Constrain to selected beatmapsets
+fn sample_query<'a>(
+    filters: &'a Filters,
+    options: &'a RandomOptions,
+    skip: &'a [i32],
+    seed: u64,
+    count: usize,
+) -> QueryBuilder<'a, Postgres> {
+    let mut builder: QueryBuilder<Postgres> = QueryBuilder::new("SELECT bs.id");
+    push_sample_conditions(&mut builder, filters, options, skip);
+    builder.push(" ORDER BY ");
+    push_sample_key(&mut builder, seed);
+    builder.push(", bs.id LIMIT ").push_bind(count as i64);
     builder
-        .push(" AND bs.id = ANY(")
-        .push_bind(&beatmapset_ids)
-        .push(")");
-    builder.push(" ORDER BY bs.id, b.id, br.id");
+}
 
-    let rows = builder.build().fetch_all(pool).await?;
+/// Weighted sample around `target`, best keys first. May return fewer than
+/// `count` sets when few difficulties are close to the target.
+async fn sample_weighted_ids(
+    pool: &PgPool,
+    filters: &Filters,
+    options: &RandomOptions,
+    target: f64,
+    seed: u64,
+    count: usize,
+) -> Result<Vec<i32>, sqlx::Error> {
+    let spread = options.rating_spread.filter(|s| *s > 0.0).unwrap_or(1.0);
+    let rating_type = preferred_rating_type(filters);
 
-    // Group by beatmapset using the common function
-    let mut beatmapsets = group_beatmapset_rows(rows)?;
+    // Matching rows inside the window only, so an index on the rating
+    // bounds the scan
+    let mut builder: QueryBuilder<Postgres> = QueryBuilder::new("SELECT bs.id");
+    builder.push(filtered_base_sql(filters));
+    apply_filters(&mut builder, filters);
+    builder
+        .push(" AND br.rating_type = ")
+        .push_bind(rating_type)
+        .push(" AND br.rating BETWEEN ")
+        .push_bind(target - WEIGHT_WINDOW * spread)
+        .push(" AND ")
+        .push_bind(target + WEIGHT_WINDOW * spread);
+    if !options.exclude_beatmapset_ids.is_empty() {
+        builder
+            .push(" AND NOT COALESCE(bs.osu_id = ANY(")
+            .push_bind(&options.exclude_beatmapset_ids)
+            .push("), FALSE)");
+    }
 
-    let preferred_type = preferred_rating_type(&filters);
-    sort_and_limit_beatmaps(&mut beatmapsets, preferred_type);
+    // ln(u) / weight, u uniform in (0, 1) from (seed, id), weight a gaussian
+    // of the closest difficulty's distance to the target
+    builder.push(" GROUP BY bs.id ORDER BY ln((('x' || left(");
+    push_sample_key(&mut builder, seed);
+    builder
+        .push(", 13))::bit(52)::int8 + 0.5) / 4503599627370496.0) / GREATEST(exp(-0.5 * power(MIN(ABS(br.rating - ")
+        .push_bind(target)
+        .push("))::float8 / ")
+        .push_bind(spread)
+        .push(", 2)), 1e-300) DESC, bs.id LIMIT ")
+        .push_bind(count as i64);
+
+    builder.build_query_scalar().fetch_all(pool).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    Ok(beatmapsets.into_values().collect())
+    #[test]
+    fn sample_orders_by_a_seeded_key() {
+        let filters = Filters::default();
+        let options = RandomOptions::default();
+        let sql = sample_query(&filters, &options, &[], 42, 9).into_sql();
+        assert!(sql.starts_with("SELECT bs.id FROM beatmapset bs WHERE EXISTS ("));
+        // $1 is the default centirate of the filters
+        assert!(sql.ends_with(" ORDER BY md5(bs.id::text || ':' || $2), bs.id LIMIT $3"));
+    }
+
+    #[test]
+    fn sample_leaves_out_excluded_and_already_picked_sets() {
+        let filters = Filters::default();
+        let options = RandomOptions {
+            exclude_beatmapset_ids: vec![1001],
+            ..Default::default()
+        };
+        let sql = sample_query(&filters, &options, &[7], 42, 9).into_sql();
+        assert!(sql.contains(") AND NOT COALESCE(bs.osu_id = ANY($2), FALSE)"));
+        assert!(sql.contains(" AND NOT (bs.id = ANY($3)) ORDER BY md5(bs.id::text || ':' || $4)"));
+    }
 }
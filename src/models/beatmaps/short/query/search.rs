@@ -0,0 +1,96 @@
+//! Free-text search (`BeatmapFilter.search_term`).
+//!
+//! A row matches when the full-text document of its beatmapset, or its
+//! difficulty name, contains the term (`websearch_to_tsquery` syntax:
+//! `"quoted phrases"`, `or`, `-excluded`); or when the term is close to the
+//! title, artist or creator by trigram word similarity, which tolerates typos.
+//! Each side is an `IN` subquery over the indexed expressions, so the search
+//! runs on the GIN indexes of `migrations/20261017000100_create_search_indexes.sql`.
+
+use sqlx::{Postgres, QueryBuilder};
+
+/// Both must stay identical to the indexed expressions of the migration.
+const DOCUMENT_SQL: &str = "beatmapset_search_document(bs.title, bs.title_unicode, bs.artist, bs.artist_unicode, bs.creator, bs.source, bs.tags)";
+const DIFFICULTY_SQL: &str = "to_tsvector('simple', b.difficulty)";
+
+/// Weights of the fuzzy matches in relevance
+const TITLE_SIMILARITY_WEIGHT: f64 = 1.0;
+const ARTIST_SIMILARITY_WEIGHT: f64 = 0.8;
+const CREATOR_SIMILARITY_WEIGHT: f64 = 0.5;
+
+/// The term without full-text operators, for trigram comparisons.
+fn fuzzy_text(term: &str) -> String {
+    term.replace('"', " ")
+        .split_whitespace()
+        .filter(|word| !word.starts_with('-') && !word.eq_ignore_ascii_case("or"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn push_tsquery<'a>(builder: &mut QueryBuilder<'a, Postgres>, term: &'a str) {
+    builder
+        .push("websearch_to_tsquery('simple', ")
+        .push_bind(term)
+        .push(")");
+}
+
+/// Push ` AND (...)` keeping rows that match `term`.
+pub fn push_search_condition<'a>(builder: &mut QueryBuilder<'a, Postgres>, term: &'a str) {
+    // The subqueries shadow `bs` / `b` so the expressions match the indexes
+    builder
+        .push(" AND (bs.id IN (SELECT bs.id FROM beatmapset bs WHERE ")
+        .push(DOCUMENT_SQL)
+        .push(" @@ ");
+    push_tsquery(builder, term);
+
+    let fuzzy = fuzzy_text(term);
+    if !fuzzy.is_empty() {
+        for column in ["bs.title", "bs.artist", "bs.creator"] {
+            builder
+                .push(" OR ")
+                .push_bind(fuzzy.clone())
+                .push(" <% ")
+                .push(column);
+        }
+    }
+    builder
+        .push(") OR b.id IN (SELECT b.id FROM beatmap b WHERE ")
+        .push(DIFFICULTY_SQL)
+        .push(" @@ ");
+    push_tsquery(builder, term);
+    builder.push("))");
+}
+
+/// Push the relevance of a row for `term`: full-text rank (title > artist >
+/// creator, source and difficulty name > tags) plus the best weighted trigram
+/// similarity. Higher is more relevant.
+pub fn push_relevance<'a>(builder: &mut QueryBuilder<'a, Postgres>, term: &'a str) {
+    builder
+        .push("(ts_rank(")
+        .push(DOCUMENT_SQL)
+        .push(" || setweight(")
+        .push(DIFFICULTY_SQL)
+        .push(", 'C'), ");
+    push_tsquery(builder, term);
+    builder.push(") + GREATEST(");
+
+    let fuzzy = fuzzy_text(term);
+    let columns = [
+        ("bs.title", TITLE_SIMILARITY_WEIGHT),
+        ("bs.artist", ARTIST_SIMILARITY_WEIGHT),
+        ("bs.creator", CREATOR_SIMILARITY_WEIGHT),
+    ];
+    for (i, (column, weight)) in columns.into_iter().enumerate() {
+        if i > 0 {
+            builder.push(", ");
+        }
+        builder
+            .push_bind(weight)
+            .push(" * word_similarity(")
+            .push_bind(fuzzy.clone())
+            .push(", ")
+            .push(column)
+            .push(")");
+    }
+    builder.push("))::float8");
+}
@@ -0,0 +1,86 @@
+use crate::filters::validate::MAX_PER_PAGE;
+use crate::filters::{Filters, ValidFilters};
+use crate::models::beatmaps::short::types::Beatmapset;
+use futures::stream::{self, Stream, TryStreamExt};
+use sqlx::{PgPool, Postgres, QueryBuilder, Transaction};
+
+use super::common::{apply_filters, apply_sort_direction};
+use super::find_all::{fetch_beatmapsets, filtered_base_sql};
+
+/// Beatmapsets fetched per round trip by the export streams.
+pub const EXPORT_CHUNK_SIZE: usize = MAX_PER_PAGE;
+
+/// Name of the server-side cursor walking the ids of an export.
+const EXPORT_CURSOR: &str = "export_ids";
+
+/// Every beatmapset matching `filters`, in the requested sort.
+///
+/// The ids are ranked once by a server-side cursor and read
+/// `EXPORT_CHUNK_SIZE` at a time, so only one chunk is held in memory
+/// whatever the catalog size. The cursor keeps a connection (and its
+/// transaction) until the stream ends or is dropped.
+/// `page`, `per_page` and `cursor` of the filters are ignored.
+pub fn stream_with_filters(
+    pool: &PgPool,
+    filters: ValidFilters,
+) -> impl Stream<Item = Result<Beatmapset, sqlx::Error>> + '_ {
+    let state: (Option<Transaction<'static, Postgres>>, Filters) = (None, export_filters(filters));
+    stream::try_unfold(state, move |(tx, filters)| async move {
+        let mut tx = match tx {
+            Some(tx) => tx,
+            None => open_export_cursor(pool, &filters).await?,
+        };
+        let ids = fetch_export_ids(&mut tx).await?;
+        if ids.is_empty() {
+            tx.commit().await?;
+            return Ok::<_, sqlx::Error>(None);
+        }
+        let chunk = fetch_beatmapsets(pool, &filters, &ids).await?;
+        Ok(Some((chunk, (Some(tx), filters))))
+    })
+    .map_ok(|chunk| stream::iter(chunk.into_iter().map(Ok)))
+    .try_flatten()
+}
+
+/// Filters set up to walk the whole result from its first set.
+pub(crate) fn export_filters(filters: ValidFilters) -> Filters {
+    let mut filters = filters.into_inner();
+    filters.page = Some(0);
+    filters.cursor = None;
+    filters.per_page = Some(EXPORT_CHUNK_SIZE);
+    filters
+}
+
+/// Start a transaction and declare the export cursor in it: the ids of
+/// every beatmapset matching `filters`, in sort order.
+pub(crate) async fn open_export_cursor(
+    pool: &PgPool,
+    filters: &Filters,
+) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    export_cursor_query(filters)
+        .build()
+        .execute(&mut *tx)
+        .await?;
+    Ok(tx)
+}
+
+/// Next chunk of ids from the export cursor, empty once it is exhausted.
+pub(crate) async fn fetch_export_ids(
+    tx: &mut Transaction<'static, Postgres>,
+) -> Result<Vec<i32>, sqlx::Error> {
+    let fetch = format!("FETCH FORWARD {} FROM {}", EXPORT_CHUNK_SIZE, EXPORT_CURSOR);
+    sqlx::query_scalar(&fetch).fetch_all(&mut **tx).await
+}
+
+fn export_cursor_query(filters: &Filters) -> QueryBuilder<'_, Postgres> {
+    let mut builder: QueryBuilder<Postgres> = QueryBuilder::new("DECLARE ");
+    builder
+        .push(EXPORT_CURSOR)
+        .push(" NO SCROLL CURSOR FOR SELECT bs.id")
+        .push(filtered_base_sql(filters));
+    apply_filters(&mut builder, filters);
+    builder.push(" GROUP BY bs.id");
+    apply_sort_direction(&mut builder, filters, false);
+    builder
+}
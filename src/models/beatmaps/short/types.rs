@@ -1,3 +1,4 @@
+use crate::enums::{GameMode, RankStatus, RatingType};
 use serde::Serialize;
 use serde_json::Value;
 use utoipa::ToSchema;
@@ -9,22 +10,64 @@ pub struct Beatmapset {
     pub title: String,
     pub creator: String,
     pub cover_url: Option<String>,
+    /// Difficulties of the set, matched or not
     pub total_beatmaps: i32,
+    /// Difficulties that matched the search, before truncation
+    pub matched_beatmaps: i32,
     pub beatmaps: Vec<Beatmap>,
 }
 
 #[derive(Serialize, Debug, Clone, ToSchema)]
 pub struct Rating {
     pub rating: f64,
-    pub rating_type: String,
+    pub rating_type: RatingType,
+    /// Rate this rating was computed at
+    pub centirate: i32,
 }
 
 #[derive(Serialize, Debug, Clone, ToSchema)]
 pub struct Beatmap {
     pub osu_id: Option<i32>,
     pub difficulty: String,
-    pub mode: i32,
-    pub status: String,
+    pub mode: GameMode,
+    pub status: RankStatus,
     pub main_pattern: Value,
+    /// Whether this difficulty matched the search; only `false` with
+    /// `TruncationPolicy::All`
+    pub matched: bool,
+    /// Rates of this beatmap that matched the search, ascending
+    pub centirates: Vec<i32>,
     pub ratings: Vec<Rating>,
 }
+
+/// Number of matching beatmapsets sharing one facet value
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct FacetCount<T> {
+    pub value: T,
+    pub count: i64,
+}
+
+/// Number of matching difficulties in `[min, max)`
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct HistogramBucket {
+    pub min: f64,
+    pub max: f64,
+    pub count: i64,
+}
+
+/// Facet counts of a search, computed under the same filters as its results
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct SearchFacets {
+    /// Beatmapsets per difficulty status
+    pub statuses: Vec<FacetCount<RankStatus>>,
+    /// Beatmapsets per `main_pattern` entry
+    pub patterns: Vec<FacetCount<String>>,
+    /// Beatmapsets per mania key count
+    pub keycounts: Vec<FacetCount<i32>>,
+    /// Rating type of the `ratings` histogram (the preferred one)
+    pub rating_type: RatingType,
+    /// Difficulties per rating bucket
+    pub ratings: Vec<HistogramBucket>,
+    /// Difficulties per BPM bucket
+    pub bpms: Vec<HistogramBucket>,
+}
@@ -0,0 +1,99 @@
+use crate::enums::{Known, RatingType};
+use crate::models::beatmaps::simple::types::{BeatmapInfo, RatingInfo};
+use bigdecimal::ToPrimitive;
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+/// The difficulty whose `.osu` file has this MD5 checksum (`rates.osu_hash`).
+/// For a rate-changed file the ratings are the ones of its centirate.
+pub async fn find_by_checksum(
+    pool: &PgPool,
+    checksum: &str,
+    rating_type: Option<RatingType>,
+) -> Result<Option<BeatmapInfo>, sqlx::Error> {
+    let mut found = find_by_checksums(pool, &[checksum], rating_type).await?;
+    Ok(found.remove(checksum).flatten())
+}
+
+/// `find_by_checksum` for many checksums in one query, keyed by the
+/// checksums as given. Every requested checksum has an entry, `None` when no
+/// rate has it.
+pub async fn find_by_checksums<S: AsRef<str>>(
+    pool: &PgPool,
+    checksums: &[S],
+    rating_type: Option<RatingType>,
+) -> Result<HashMap<String, Option<BeatmapInfo>>, sqlx::Error> {
+    if checksums.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let normalized: Vec<String> = checksums
+        .iter()
+        .map(|c| c.as_ref().trim().to_ascii_lowercase())
+        .collect();
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            r.osu_hash AS r_osu_hash,
+            b.osu_id AS b_osu_id,
+            b.difficulty AS b_difficulty,
+            b.count_circles AS b_count_circles,
+            b.count_sliders AS b_count_sliders,
+            b.count_spinners AS b_count_spinners,
+            b.od AS b_od,
+            b.hp AS b_hp,
+            b.main_pattern AS b_main_pattern,
+            br.rating_type AS "br_rating_type?: Known<RatingType>",
+            br.rating AS "br_rating?"
+        FROM rates r
+        INNER JOIN beatmap b ON b.id = r.beatmap_id
+        LEFT JOIN beatmap_rating br ON r.id = br.rates_id
+            AND ($2::text IS NULL OR br.rating_type = $2::text)
+        WHERE r.osu_hash = ANY($1)
+        ORDER BY r.osu_hash, br.rating_type
+        "#,
+        &normalized,
+        rating_type.as_ref().map(RatingType::as_str)
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut found: HashMap<&str, BeatmapInfo> = HashMap::new();
+    for row in &rows {
+        let beatmap_info = found
+            .entry(row.r_osu_hash.as_str())
+            .or_insert_with(|| BeatmapInfo {
+                beatmap_osu_id: row.b_osu_id.unwrap_or(0),
+                name: row.b_difficulty.clone(),
+                count_circles: row.b_count_circles,
+                count_sliders: row.b_count_sliders,
+                count_spinners: row.b_count_spinners,
+                od: row.b_od.to_f64().unwrap_or(0.0),
+                hp: row.b_hp.to_f64().unwrap_or(0.0),
+                main_pattern: row.b_main_pattern.clone(),
+                ratings: Vec::new(),
+            });
+
+        // Skip rows without a valid rating
+        let Some(Known(Some(rating_type))) = row.br_rating_type else {
+            continue;
+        };
+        beatmap_info.ratings.push(RatingInfo {
+            rating_type,
+            rating_value: row
+                .br_rating
+                .as_ref()
+                .and_then(|v| v.to_f64())
+                .unwrap_or(0.0),
+        });
+    }
+
+    Ok(checksums
+        .iter()
+        .zip(&normalized)
+        .map(|(checksum, hash)| {
+            let info = found.get(hash.as_str()).cloned();
+            (checksum.as_ref().to_string(), info)
+        })
+        .collect())
+}
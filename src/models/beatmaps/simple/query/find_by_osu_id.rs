@@ -1,14 +1,29 @@
+use crate::enums::{Known, RatingType};
 use crate::models::beatmaps::simple::types::{BeatmapInfo, Beatmapset, RatingInfo};
 use bigdecimal::ToPrimitive;
-use serde_json;
 use sqlx::PgPool;
 use std::collections::HashMap;
 
 pub async fn find_by_osu_id(
     pool: &PgPool,
     osu_id: i32,
-    rating_type: Option<String>,
+    rating_type: Option<RatingType>,
 ) -> Result<Option<Beatmapset>, sqlx::Error> {
+    let mut found = find_by_osu_ids(pool, &[osu_id], rating_type).await?;
+    Ok(found.remove(&osu_id).flatten())
+}
+
+/// `find_by_osu_id` for many beatmapset osu! ids in one query. Every
+/// requested id has an entry, `None` when the beatmapset does not exist.
+pub async fn find_by_osu_ids(
+    pool: &PgPool,
+    osu_ids: &[i32],
+    rating_type: Option<RatingType>,
+) -> Result<HashMap<i32, Option<Beatmapset>>, sqlx::Error> {
+    if osu_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
     let rows = sqlx::query!(
         r#"
         SELECT
@@ -36,81 +51,85 @@ pub async fn find_by_osu_id(
             b.od AS b_od,
             b.hp AS b_hp,
             b.main_pattern AS b_main_pattern,
-            br.rating_type AS br_rating_type,
+            br.rating_type AS "br_rating_type?: Known<RatingType>",
             br.rating AS br_rating
         FROM beatmapset bs
         INNER JOIN beatmap b ON bs.id = b.beatmapset_id
         INNER JOIN rates r ON b.id = r.beatmap_id AND r.centirate = 100
         LEFT JOIN beatmap_rating br ON r.id = br.rates_id
-        WHERE bs.osu_id = $1
+        WHERE bs.osu_id = ANY($1)
         AND ($2::text IS NULL OR br.rating_type = $2::text)
-        ORDER BY b.osu_id, br.rating_type
+        ORDER BY bs.id, b.osu_id, br.rating_type
         "#,
-        osu_id,
-        rating_type
+        osu_ids,
+        rating_type.as_ref().map(RatingType::as_str)
     )
     .fetch_all(pool)
     .await?;
 
-    if rows.is_empty() {
-        return Ok(None);
-    }
-
-    let first_row = &rows[0];
-
-    // Grouper les résultats par beatmap
-    let mut beatmaps_map = HashMap::new();
+    // Grouper les résultats par beatmapset puis par beatmap
+    let mut beatmapsets: HashMap<i32, Beatmapset> = HashMap::new();
 
     for row in &rows {
-        let beatmap_osu_id = row.b_osu_id;
-        let difficulty = row.b_difficulty.clone();
-        let rating_type = row.br_rating_type.clone();
-        let rating_value = row.br_rating.to_f64().unwrap_or(0.0);
+        let Some(bs_osu_id) = row.bs_osu_id else {
+            continue;
+        };
+        let beatmapset = beatmapsets.entry(bs_osu_id).or_insert_with(|| Beatmapset {
+            id: row.bs_id,
+            osu_id: row.bs_osu_id,
+            artist: row.bs_artist.clone(),
+            artist_unicode: row.bs_artist_unicode.clone(),
+            title: row.bs_title.clone(),
+            title_unicode: row.bs_title_unicode.clone(),
+            creator: row.bs_creator.clone(),
+            source: row.bs_source.clone(),
+            tags: row.bs_tags.clone(),
+            has_video: row.bs_has_video,
+            has_storyboard: row.bs_has_storyboard,
+            is_explicit: row.bs_is_explicit,
+            is_featured: row.bs_is_featured,
+            cover_url: row.bs_cover_url.clone(),
+            preview_url: row.bs_preview_url.clone(),
+            osu_file_url: row.bs_osu_file_url.clone(),
+            beatmaps: Vec::new(),
+        });
 
-        // Skip if rating_type is empty (no valid rating)
-        if rating_type.is_empty() {
+        // Skip rows without a valid rating
+        let Some(Known(Some(rating_type))) = row.br_rating_type else {
             continue;
-        }
+        };
 
-        let beatmap_info = beatmaps_map
-            .entry(beatmap_osu_id)
-            .or_insert_with(|| BeatmapInfo {
-                beatmap_osu_id: beatmap_osu_id.unwrap_or(0),
-                name: difficulty,
-                count_circles: row.b_count_circles,
-                count_sliders: row.b_count_sliders,
-                count_spinners: row.b_count_spinners,
-                od: row.b_od.to_f64().unwrap_or(0.0),
-                hp: row.b_hp.to_f64().unwrap_or(0.0),
-                main_pattern: row.b_main_pattern.clone(),
-                ratings: Vec::new(),
-            });
+        let beatmap_osu_id = row.b_osu_id.unwrap_or(0);
+        let index = match beatmapset
+            .beatmaps
+            .iter()
+            .position(|b| b.beatmap_osu_id == beatmap_osu_id)
+        {
+            Some(index) => index,
+            None => {
+                beatmapset.beatmaps.push(BeatmapInfo {
+                    beatmap_osu_id,
+                    name: row.b_difficulty.clone(),
+                    count_circles: row.b_count_circles,
+                    count_sliders: row.b_count_sliders,
+                    count_spinners: row.b_count_spinners,
+                    od: row.b_od.to_f64().unwrap_or(0.0),
+                    hp: row.b_hp.to_f64().unwrap_or(0.0),
+                    main_pattern: row.b_main_pattern.clone(),
+                    ratings: Vec::new(),
+                });
+                beatmapset.beatmaps.len() - 1
+            }
+        };
 
-        beatmap_info.ratings.push(RatingInfo {
+        beatmapset.beatmaps[index].ratings.push(RatingInfo {
             rating_type,
-            rating_value,
+            rating_value: row.br_rating.to_f64().unwrap_or(0.0),
         });
     }
 
-    let beatmaps = beatmaps_map.into_values().collect();
-
-    Ok(Some(Beatmapset {
-        id: first_row.bs_id,
-        osu_id: first_row.bs_osu_id,
-        artist: first_row.bs_artist.clone(),
-        artist_unicode: first_row.bs_artist_unicode.clone(),
-        title: first_row.bs_title.clone(),
-        title_unicode: first_row.bs_title_unicode.clone(),
-        creator: first_row.bs_creator.clone(),
-        source: first_row.bs_source.clone(),
-        tags: first_row.bs_tags.clone(),
-        has_video: first_row.bs_has_video,
-        has_storyboard: first_row.bs_has_storyboard,
-        is_explicit: first_row.bs_is_explicit,
-        is_featured: first_row.bs_is_featured,
-        cover_url: first_row.bs_cover_url.clone(),
-        preview_url: first_row.bs_preview_url.clone(),
-        osu_file_url: first_row.bs_osu_file_url.clone(),
-        beatmaps,
-    }))
+    Ok(osu_ids
+        .iter()
+        .map(|osu_id| (*osu_id, beatmapsets.get(osu_id).cloned()))
+        .collect())
 }
@@ -1 +1,2 @@
+pub mod find_by_checksum;
 pub mod find_by_osu_id;
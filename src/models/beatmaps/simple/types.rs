@@ -1,3 +1,4 @@
+use crate::enums::RatingType;
 use serde::Serialize;
 use utoipa::ToSchema;
 
@@ -37,6 +38,6 @@ pub struct BeatmapInfo {
 
 #[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct RatingInfo {
-    pub rating_type: String,
+    pub rating_type: RatingType,
     pub rating_value: f64,
 }
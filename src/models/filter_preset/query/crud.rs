@@ -0,0 +1,104 @@
+use crate::filters::Filters;
+use crate::models::filter_preset::types::SavedFilterPreset;
+use chrono::NaiveDateTime;
+use sqlx::PgPool;
+
+type PresetRow = (i32, String, String, NaiveDateTime);
+
+fn from_row((id, name, token, created_at): PresetRow) -> SavedFilterPreset {
+    SavedFilterPreset {
+        id,
+        name,
+        token,
+        created_at,
+    }
+}
+
+pub async fn create_filter_preset(
+    pool: &PgPool,
+    name: &str,
+    filters: &Filters,
+) -> Result<SavedFilterPreset, sqlx::Error> {
+    let row: PresetRow = sqlx::query_as(
+        r#"
+        INSERT INTO filter_preset (name, token)
+        VALUES ($1, $2)
+        RETURNING id, name, token, created_at
+        "#,
+    )
+    .bind(name)
+    .bind(filters.to_token())
+    .fetch_one(pool)
+    .await?;
+
+    Ok(from_row(row))
+}
+
+pub async fn find_filter_preset_by_id(
+    pool: &PgPool,
+    id: i32,
+) -> Result<Option<SavedFilterPreset>, sqlx::Error> {
+    let row: Option<PresetRow> =
+        sqlx::query_as("SELECT id, name, token, created_at FROM filter_preset WHERE id = $1")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?;
+
+    Ok(row.map(from_row))
+}
+
+/// Most recent presets first.
+pub async fn find_all_filter_presets(
+    pool: &PgPool,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<SavedFilterPreset>, sqlx::Error> {
+    let rows: Vec<PresetRow> = sqlx::query_as(
+        r#"
+        SELECT id, name, token, created_at
+        FROM filter_preset
+        ORDER BY created_at DESC, id DESC
+        LIMIT $1 OFFSET $2
+        "#,
+    )
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(from_row).collect())
+}
+
+/// Rename a preset and replace its filters, `None` if it does not exist.
+pub async fn update_filter_preset(
+    pool: &PgPool,
+    id: i32,
+    name: &str,
+    filters: &Filters,
+) -> Result<Option<SavedFilterPreset>, sqlx::Error> {
+    let row: Option<PresetRow> = sqlx::query_as(
+        r#"
+        UPDATE filter_preset
+        SET name = $2, token = $3
+        WHERE id = $1
+        RETURNING id, name, token, created_at
+        "#,
+    )
+    .bind(id)
+    .bind(name)
+    .bind(filters.to_token())
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(from_row))
+}
+
+/// Returns `false` when no preset had this id.
+pub async fn delete_filter_preset(pool: &PgPool, id: i32) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM filter_preset WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
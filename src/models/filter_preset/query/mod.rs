@@ -0,0 +1,3 @@
+pub mod crud;
+
+pub use crud::*;
@@ -0,0 +1,31 @@
+use crate::filters::{Filters, TokenError};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A named search saved as a filter token.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[schema(
+    title = "SavedFilterPreset",
+    description = "Saved search, stored as a shareable filter token"
+)]
+pub struct SavedFilterPreset {
+    pub id: i32,
+
+    /// Display name chosen by the user.
+    #[schema(example = "4K stream 20+")]
+    pub name: String,
+
+    /// Token produced by `Filters::to_token`.
+    #[schema(example = "v1.cmF0aW5nLm1pbj0z")]
+    pub token: String,
+
+    pub created_at: NaiveDateTime,
+}
+
+impl SavedFilterPreset {
+    /// Decode the stored token back into `Filters`.
+    pub fn filters(&self) -> Result<Filters, TokenError> {
+        Filters::from_token(&self.token)
+    }
+}
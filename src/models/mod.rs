@@ -1,3 +1,4 @@
 pub mod beatmaps;
+pub mod filter_preset;
 pub mod pending_beatmap;
 pub mod rate;
@@ -0,0 +1,386 @@
+use crate::enums::Skillset;
+use crate::models::rate::{ManiaRating, ModeRating, RateTarget, Rates, Rating};
+
+/// Slack on band edges, interpolated values are not exact
+pub(crate) const EPSILON: f64 = 1e-9;
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+fn lerp_mania(a: &ManiaRating, b: &ManiaRating, t: f64) -> ManiaRating {
+    ManiaRating {
+        id: None,
+        stream: lerp(a.stream, b.stream, t),
+        jumpstream: lerp(a.jumpstream, b.jumpstream, t),
+        handstream: lerp(a.handstream, b.handstream, t),
+        stamina: lerp(a.stamina, b.stamina, t),
+        jackspeed: lerp(a.jackspeed, b.jackspeed, t),
+        chordjack: lerp(a.chordjack, b.chordjack, t),
+        technical: lerp(a.technical, b.technical, t),
+    }
+}
+
+impl ManiaRating {
+    pub fn skillset(&self, skillset: Skillset) -> f64 {
+        match skillset {
+            Skillset::Stream => self.stream,
+            Skillset::Jumpstream => self.jumpstream,
+            Skillset::Handstream => self.handstream,
+            Skillset::Stamina => self.stamina,
+            Skillset::Jackspeed => self.jackspeed,
+            Skillset::Chordjack => self.chordjack,
+            Skillset::Technical => self.technical,
+        }
+    }
+}
+
+impl RateTarget {
+    /// Value of `rate` for this target, `None` when it has no such rating
+    /// (or no mania skillsets for a skillset target).
+    pub fn value(&self, rate: &Rates) -> Option<f64> {
+        let rating = rate
+            .rating
+            .iter()
+            .find(|r| r.rating_type == self.rating_type)?;
+        match (self.skillset, &rating.mode_rating) {
+            (None, _) => Some(rating.rating),
+            (Some(skillset), ModeRating::Mania(mania)) => Some(mania.skillset(skillset)),
+            (Some(_), _) => None,
+        }
+    }
+
+    pub fn contains(&self, value: f64) -> bool {
+        value >= self.min - EPSILON && self.max.is_none_or(|max| value <= max + EPSILON)
+    }
+}
+
+impl Rates {
+    /// BPM and times of this rate played at `centirate` instead, ratings left
+    /// out. Exact up to the rounding of this rate's own times, so scale from
+    /// the 1.0x rate when it is stored.
+    pub fn scaled_to(&self, centirate: i32) -> Rates {
+        let factor = centirate as f64 / self.centirate as f64;
+        Rates {
+            id: None,
+            osu_hash: None,
+            centirate,
+            drain_time: (self.drain_time as f64 / factor).round() as i32,
+            total_time: (self.total_time as f64 / factor).round() as i32,
+            bpm: (self.bpm as f64 * factor) as f32,
+            rating: Vec::new(),
+            estimated: true,
+        }
+    }
+
+    /// Estimated rate at `centirate`, between the stored `lower` and `upper`
+    /// rates: ratings (overall and mania skillsets) are interpolated linearly,
+    /// per rating type present at both rates.
+    pub fn interpolate(lower: &Rates, upper: &Rates, centirate: i32) -> Rates {
+        let mut rate = lower.scaled_to(centirate);
+        let span = (upper.centirate - lower.centirate) as f64;
+        let t = if span == 0.0 {
+            0.0
+        } else {
+            (centirate - lower.centirate) as f64 / span
+        };
+
+        rate.rating = lower
+            .rating
+            .iter()
+            .filter_map(|low| {
+                let high = upper
+                    .rating
+                    .iter()
+                    .find(|r| r.rating_type == low.rating_type)?;
+                let mode_rating = match (&low.mode_rating, &high.mode_rating) {
+                    (ModeRating::Mania(a), ModeRating::Mania(b)) => {
+                        ModeRating::Mania(lerp_mania(a, b, t))
+                    }
+                    (other, _) => other.clone(),
+                };
+                Some(Rating {
+                    id: None,
+                    rates_id: None,
+                    rating: lerp(low.rating, high.rating, t),
+                    rating_type: low.rating_type,
+                    mode_rating,
+                })
+            })
+            .collect();
+        rate
+    }
+}
+
+/// The rate at `centirate` from the stored `rates` of one beatmap: the stored
+/// one when it exists, otherwise an estimate between its closest neighbours.
+/// `None` outside the stored range.
+pub fn estimate_rate(rates: &[Rates], centirate: i32) -> Option<Rates> {
+    if let Some(exact) = rates.iter().find(|r| r.centirate == centirate) {
+        return Some(exact.clone());
+    }
+    let lower = rates
+        .iter()
+        .filter(|r| r.centirate < centirate)
+        .max_by_key(|r| r.centirate)?;
+    let upper = rates
+        .iter()
+        .filter(|r| r.centirate > centirate)
+        .min_by_key(|r| r.centirate)?;
+    Some(estimate_between(rates, lower, upper, centirate))
+}
+
+/// `Rates::interpolate`, with BPM and times scaled from the stored 1.0x rate
+/// when there is one: the other stored rates were already rounded once.
+fn estimate_between(rates: &[Rates], lower: &Rates, upper: &Rates, centirate: i32) -> Rates {
+    let estimate = Rates::interpolate(lower, upper, centirate);
+    match rates.iter().find(|r| r.centirate == 100) {
+        Some(base) => Rates {
+            rating: estimate.rating,
+            ..base.scaled_to(centirate)
+        },
+        None => estimate,
+    }
+}
+
+/// The lowest rate of one beatmap whose `target` value is inside the band:
+/// a stored rate, or an estimate at the whole centirate where the
+/// interpolation between two stored rates enters the band. `None` when no
+/// rate in the stored range gets there.
+pub fn solve_centirate(rates: &[Rates], target: &RateTarget) -> Option<Rates> {
+    let mut points: Vec<(&Rates, f64)> = rates
+        .iter()
+        .filter_map(|rate| Some((rate, target.value(rate)?)))
+        .collect();
+    points.sort_by_key(|(rate, _)| rate.centirate);
+
+    for (i, (rate, value)) in points.iter().enumerate() {
+        if target.contains(*value) {
+            return Some((*rate).clone());
+        }
+        let Some((next, next_value)) = points.get(i + 1) else {
+            break;
+        };
+        // Band edge crossed on the way to the next stored rate, if any
+        let edge = match target.max {
+            Some(max) if *value > max => max,
+            _ => target.min,
+        };
+        let t = (edge - value) / (next_value - value);
+        if !(0.0..=1.0).contains(&t) {
+            continue;
+        }
+        let step = ((next.centirate - rate.centirate) as f64 * t - EPSILON).ceil() as i32;
+        let centirate = rate.centirate + step.max(1);
+        if centirate >= next.centirate {
+            // The stored rate itself, checked next
+            continue;
+        }
+        let estimate = estimate_between(rates, rate, next, centirate);
+        if target.value(&estimate).is_some_and(|v| target.contains(v)) {
+            return Some(estimate);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enums::RatingType;
+
+    /// Base times at 1.0x, stored rates round them like the pipeline does.
+    const DRAIN: f64 = 100.0;
+    const BPM: f64 = 150.0;
+
+    fn mania(value: f64) -> ManiaRating {
+        ManiaRating {
+            id: None,
+            stream: value,
+            jumpstream: value,
+            handstream: value,
+            stamina: value,
+            jackspeed: value,
+            chordjack: value,
+            technical: value,
+        }
+    }
+
+    fn rating(rating_type: RatingType, value: f64) -> Rating {
+        Rating {
+            id: None,
+            rates_id: None,
+            rating: value,
+            rating_type,
+            mode_rating: ModeRating::Mania(mania(value)),
+        }
+    }
+
+    fn stored(centirate: i32, ratings: Vec<Rating>) -> Rates {
+        let factor = centirate as f64 / 100.0;
+        Rates {
+            id: Some(centirate),
+            osu_hash: Some(format!("hash{}", centirate)),
+            centirate,
+            drain_time: (DRAIN / factor).round() as i32,
+            total_time: (DRAIN / factor).round() as i32,
+            bpm: (BPM * factor) as f32,
+            rating: ratings,
+            estimated: false,
+        }
+    }
+
+    fn etterna(centirate: i32, value: f64) -> Rates {
+        stored(centirate, vec![rating(RatingType::Etterna, value)])
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn interpolate_is_linear_between_neighbours() {
+        let rate = Rates::interpolate(&etterna(100, 20.0), &etterna(120, 24.0), 105);
+        assert_eq!(rate.centirate, 105);
+        assert!(rate.estimated);
+        assert_eq!((rate.id, rate.osu_hash.as_deref()), (None, None));
+        assert_close(rate.rating[0].rating, 21.0);
+        let ModeRating::Mania(mania) = &rate.rating[0].mode_rating else {
+            panic!("mania skillsets were dropped");
+        };
+        assert_close(mania.stream, 21.0);
+        assert_close(rate.bpm as f64, 157.5);
+    }
+
+    #[test]
+    fn interpolate_keeps_rating_types_present_at_both_rates() {
+        let lower = stored(
+            100,
+            vec![
+                rating(RatingType::Osu, 3.0),
+                rating(RatingType::Etterna, 20.0),
+            ],
+        );
+        let upper = etterna(110, 22.0);
+        let rate = Rates::interpolate(&lower, &upper, 105);
+        assert_eq!(rate.rating.len(), 1);
+        assert_eq!(rate.rating[0].rating_type, RatingType::Etterna);
+    }
+
+    #[test]
+    fn estimate_returns_stored_rates_as_is() {
+        let rates = [etterna(100, 20.0), etterna(120, 24.0)];
+        let rate = estimate_rate(&rates, 120).unwrap();
+        assert!(!rate.estimated);
+        assert_eq!(rate.id, Some(120));
+    }
+
+    #[test]
+    fn estimate_is_none_outside_the_stored_range() {
+        let rates = [etterna(100, 20.0), etterna(120, 24.0)];
+        assert!(estimate_rate(&rates, 95).is_none());
+        assert!(estimate_rate(&rates, 121).is_none());
+        assert!(estimate_rate(&[], 100).is_none());
+    }
+
+    #[test]
+    fn estimate_scales_times_from_the_1x_rate() {
+        // 110 stores round(100 / 1.1) = 91; 91 * 1.1 / 1.13 would round to 89
+        let rates = [etterna(100, 20.0), etterna(110, 22.0), etterna(120, 24.0)];
+        let rate = estimate_rate(&rates, 113).unwrap();
+        assert_eq!(rate.drain_time, 88);
+        assert_close(rate.rating[0].rating, 22.6);
+
+        // Without a 1.0x rate, the lower neighbour is the best there is
+        let rate = estimate_rate(&rates[1..], 113).unwrap();
+        assert_eq!(rate.drain_time, 89);
+    }
+
+    fn target(min: f64, max: Option<f64>) -> RateTarget {
+        RateTarget {
+            rating_type: RatingType::Etterna,
+            skillset: None,
+            min,
+            max,
+        }
+    }
+
+    #[test]
+    fn solve_prefers_the_lowest_stored_rate_in_the_band() {
+        let rates = [etterna(120, 24.0), etterna(100, 20.0), etterna(110, 22.0)];
+        let rate = solve_centirate(&rates, &target(22.0, None)).unwrap();
+        assert_eq!(rate.centirate, 110);
+        assert!(!rate.estimated);
+    }
+
+    #[test]
+    fn solve_finds_the_first_whole_centirate_past_the_edge() {
+        let rates = [etterna(100, 20.0), etterna(120, 24.0)];
+        // 22.1 is crossed at 110.5
+        let rate = solve_centirate(&rates, &target(22.1, None)).unwrap();
+        assert_eq!(rate.centirate, 111);
+        assert!(rate.estimated);
+        assert_close(rate.rating[0].rating, 22.2);
+
+        // Exactly on a whole centirate
+        let rate = solve_centirate(&rates, &target(22.0, None)).unwrap();
+        assert_eq!(rate.centirate, 110);
+    }
+
+    #[test]
+    fn solve_walks_down_to_a_maximum() {
+        let rates = [etterna(100, 30.0), etterna(120, 20.0)];
+        let rate = solve_centirate(&rates, &target(0.0, Some(25.0))).unwrap();
+        assert_eq!(rate.centirate, 110);
+    }
+
+    #[test]
+    fn solve_skips_flat_segments() {
+        let rates = [etterna(100, 20.0), etterna(110, 20.0), etterna(120, 24.0)];
+        let rate = solve_centirate(&rates, &target(22.0, None)).unwrap();
+        assert_eq!(rate.centirate, 115);
+
+        // The band edge sits on the flat part itself
+        let rate = solve_centirate(&rates, &target(20.0, Some(20.0))).unwrap();
+        assert_eq!(rate.centirate, 100);
+
+        let flat = [etterna(100, 20.0), etterna(110, 20.0)];
+        assert!(solve_centirate(&flat, &target(21.0, None)).is_none());
+    }
+
+    #[test]
+    fn solve_is_none_for_a_band_between_two_centirates() {
+        // 0.2 per centirate: [22.05, 22.15] lies strictly between 110 and 111
+        let rates = [etterna(100, 20.0), etterna(120, 24.0)];
+        assert!(solve_centirate(&rates, &target(22.05, Some(22.15))).is_none());
+    }
+
+    #[test]
+    fn solve_is_none_outside_the_stored_range() {
+        let rates = [etterna(100, 20.0), etterna(120, 24.0)];
+        assert!(solve_centirate(&rates, &target(24.5, None)).is_none());
+        assert!(solve_centirate(&rates, &target(10.0, Some(15.0))).is_none());
+        assert!(solve_centirate(&[], &target(20.0, None)).is_none());
+    }
+
+    #[test]
+    fn solve_ignores_rates_without_the_target_rating() {
+        let rates = [
+            stored(100, vec![rating(RatingType::Osu, 3.0)]),
+            etterna(110, 22.0),
+        ];
+        let rate = solve_centirate(&rates, &target(2.0, None)).unwrap();
+        assert_eq!(rate.centirate, 110);
+
+        let skillset = RateTarget {
+            skillset: Some(Skillset::Stream),
+            ..target(22.0, None)
+        };
+        assert_eq!(solve_centirate(&rates, &skillset).unwrap().centirate, 110);
+    }
+}
@@ -1,5 +1,7 @@
+pub mod r#impl;
 pub mod query;
 pub mod types;
 
 pub use query::*;
+pub use r#impl::{estimate_rate, solve_centirate};
 pub use types::*;
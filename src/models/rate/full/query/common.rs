@@ -0,0 +1,141 @@
+use crate::enums::{GameMode, Known, RatingType};
+use crate::models::rate::{ManiaRating, ModeRating, Rates, Rating};
+use bigdecimal::{BigDecimal, ToPrimitive};
+use std::collections::HashMap;
+
+pub(crate) fn decimal_to_f64(value: &Option<BigDecimal>) -> f64 {
+    value.as_ref().and_then(|v| v.to_f64()).unwrap_or_default()
+}
+
+/// One joined row beatmap / rate / rating / mania rating.
+#[derive(Debug)]
+pub(crate) struct RateDetailRow {
+    pub b_id: i32,
+    pub b_osu_id: Option<i32>,
+    pub b_mode: Known<GameMode>,
+    pub r_id: Option<i32>,
+    pub r_osu_hash: Option<String>,
+    pub r_centirate: Option<i32>,
+    pub r_drain_time: Option<i32>,
+    pub r_total_time: Option<i32>,
+    pub r_bpm: Option<BigDecimal>,
+    pub br_id: Option<i32>,
+    pub br_rates_id: Option<i32>,
+    pub br_rating: Option<BigDecimal>,
+    pub br_rating_type: Option<Known<RatingType>>,
+    pub bmr_id: Option<i32>,
+    pub bmr_stream: Option<BigDecimal>,
+    pub bmr_jumpstream: Option<BigDecimal>,
+    pub bmr_handstream: Option<BigDecimal>,
+    pub bmr_stamina: Option<BigDecimal>,
+    pub bmr_jackspeed: Option<BigDecimal>,
+    pub bmr_chordjack: Option<BigDecimal>,
+    pub bmr_technical: Option<BigDecimal>,
+}
+
+impl RateDetailRow {
+    fn rate(&self, r_id: i32) -> Rates {
+        Rates {
+            id: Some(r_id),
+            osu_hash: self.r_osu_hash.clone(),
+            centirate: self.r_centirate.unwrap_or_default(),
+            drain_time: self.r_drain_time.unwrap_or_default(),
+            total_time: self.r_total_time.unwrap_or_default(),
+            bpm: decimal_to_f64(&self.r_bpm) as f32,
+            rating: Vec::new(),
+            estimated: false,
+        }
+    }
+
+    fn rating(&self, br_id: i32) -> Option<Rating> {
+        Some(Rating {
+            id: Some(br_id),
+            rates_id: self.br_rates_id.or(self.r_id),
+            rating: decimal_to_f64(&self.br_rating),
+            rating_type: self.br_rating_type?.0?,
+            mode_rating: ModeRating::Mania(ManiaRating {
+                id: self.bmr_id,
+                stream: decimal_to_f64(&self.bmr_stream),
+                jumpstream: decimal_to_f64(&self.bmr_jumpstream),
+                handstream: decimal_to_f64(&self.bmr_handstream),
+                stamina: decimal_to_f64(&self.bmr_stamina),
+                jackspeed: decimal_to_f64(&self.bmr_jackspeed),
+                chordjack: decimal_to_f64(&self.bmr_chordjack),
+                technical: decimal_to_f64(&self.bmr_technical),
+            }),
+        })
+    }
+}
+
+/// Group joined rows into the rates of each beatmap (internal id), in row
+/// order. Beatmaps without any rate get an empty list, beatmaps of an unknown
+/// mode are skipped.
+pub(crate) fn group_rate_rows(rows: Vec<RateDetailRow>) -> HashMap<i32, Vec<Rates>> {
+    let mut rates_by_beatmap: HashMap<i32, Vec<Rates>> = HashMap::new();
+    // rate id -> index in its beatmap's rates
+    let mut rate_index: HashMap<i32, usize> = HashMap::new();
+
+    for row in rows {
+        let Known(Some(_)) = row.b_mode else {
+            continue;
+        };
+        let rates = rates_by_beatmap.entry(row.b_id).or_default();
+        let Some(r_id) = row.r_id else {
+            continue;
+        };
+        let r = *rate_index.entry(r_id).or_insert_with(|| {
+            rates.push(row.rate(r_id));
+            rates.len() - 1
+        });
+        if let Some(rating) = row.br_id.and_then(|br_id| row.rating(br_id)) {
+            rates[r].rating.push(rating);
+        }
+    }
+
+    rates_by_beatmap
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(b_id: i32, mode: Option<GameMode>, rating_type: Option<RatingType>) -> RateDetailRow {
+        RateDetailRow {
+            b_id,
+            b_osu_id: Some(b_id),
+            b_mode: Known(mode),
+            r_id: Some(b_id * 10),
+            r_osu_hash: None,
+            r_centirate: Some(100),
+            r_drain_time: None,
+            r_total_time: None,
+            r_bpm: None,
+            br_id: Some(b_id * 100),
+            br_rates_id: Some(b_id * 10),
+            br_rating: None,
+            br_rating_type: Some(Known(rating_type)),
+            bmr_id: None,
+            bmr_stream: None,
+            bmr_jumpstream: None,
+            bmr_handstream: None,
+            bmr_stamina: None,
+            bmr_jackspeed: None,
+            bmr_chordjack: None,
+            bmr_technical: None,
+        }
+    }
+
+    #[test]
+    fn skips_unknown_modes_and_rating_types() {
+        let rates = group_rate_rows(vec![
+            row(1, Some(GameMode::Mania), Some(RatingType::Etterna)),
+            row(2, None, Some(RatingType::Etterna)),
+            row(3, Some(GameMode::Mania), None),
+        ]);
+
+        assert_eq!(rates[&1][0].rating.len(), 1);
+        assert!(!rates.contains_key(&2));
+        assert_eq!(rates[&3].len(), 1);
+        assert!(rates[&3][0].rating.is_empty());
+    }
+}
@@ -0,0 +1,160 @@
+use crate::enums::{GameMode, Known, RatingType};
+use crate::models::rate::Rates;
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+use super::common::{group_rate_rows, RateDetailRow};
+
+/// Every stored rate of a beatmap, ascending by centirate, with its ratings
+/// and mania skillsets. Empty when the beatmap is unknown or has no rates.
+pub async fn find_rates_by_beatmap_osu_id(
+    pool: &PgPool,
+    beatmap_osu_id: i32,
+) -> Result<Vec<Rates>, sqlx::Error> {
+    let rows = sqlx::query_as!(
+        RateDetailRow,
+        r#"
+        SELECT
+            b.id                     AS b_id,
+            b.osu_id                 AS b_osu_id,
+            b.mode                   AS "b_mode: Known<GameMode>",
+            r.id                     AS "r_id?",
+            r.osu_hash               AS "r_osu_hash?",
+            r.centirate              AS "r_centirate?",
+            r.drain_time             AS "r_drain_time?",
+            r.total_time             AS "r_total_time?",
+            r.bpm                    AS "r_bpm?",
+            br.id                    AS "br_id?",
+            br.rates_id              AS "br_rates_id?",
+            br.rating                AS "br_rating?",
+            br.rating_type           AS "br_rating_type?: Known<RatingType>",
+            bmr.id                   AS "bmr_id?",
+            bmr.stream               AS "bmr_stream?",
+            bmr.jumpstream           AS "bmr_jumpstream?",
+            bmr.handstream           AS "bmr_handstream?",
+            bmr.stamina              AS "bmr_stamina?",
+            bmr.jackspeed            AS "bmr_jackspeed?",
+            bmr.chordjack            AS "bmr_chordjack?",
+            bmr.technical            AS "bmr_technical?"
+        FROM beatmap b
+        INNER JOIN rates r ON r.beatmap_id = b.id
+        LEFT JOIN beatmap_rating br ON br.rates_id = r.id
+        LEFT JOIN beatmap_mania_rating bmr ON bmr.rating_id = br.id
+        WHERE b.osu_id = $1
+        ORDER BY r.centirate ASC, r.id ASC, br.id ASC
+        "#,
+        beatmap_osu_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(group_rate_rows(rows)
+        .into_values()
+        .next()
+        .unwrap_or_default())
+}
+
+/// `find_rates_by_beatmap_osu_id` for every difficulty of a beatmapset in one
+/// query, keyed by beatmap osu! id. Difficulties without rates map to an
+/// empty list; difficulties without an osu! id are left out.
+pub async fn find_rates_by_beatmapset_osu_id(
+    pool: &PgPool,
+    beatmapset_osu_id: i32,
+) -> Result<HashMap<i32, Vec<Rates>>, sqlx::Error> {
+    let rows = sqlx::query_as!(
+        RateDetailRow,
+        r#"
+        SELECT
+            b.id                     AS b_id,
+            b.osu_id                 AS b_osu_id,
+            b.mode                   AS "b_mode: Known<GameMode>",
+            r.id                     AS "r_id?",
+            r.osu_hash               AS "r_osu_hash?",
+            r.centirate              AS "r_centirate?",
+            r.drain_time             AS "r_drain_time?",
+            r.total_time             AS "r_total_time?",
+            r.bpm                    AS "r_bpm?",
+            br.id                    AS "br_id?",
+            br.rates_id              AS "br_rates_id?",
+            br.rating                AS "br_rating?",
+            br.rating_type           AS "br_rating_type?: Known<RatingType>",
+            bmr.id                   AS "bmr_id?",
+            bmr.stream               AS "bmr_stream?",
+            bmr.jumpstream           AS "bmr_jumpstream?",
+            bmr.handstream           AS "bmr_handstream?",
+            bmr.stamina              AS "bmr_stamina?",
+            bmr.jackspeed            AS "bmr_jackspeed?",
+            bmr.chordjack            AS "bmr_chordjack?",
+            bmr.technical            AS "bmr_technical?"
+        FROM beatmapset bs
+        INNER JOIN beatmap b ON b.beatmapset_id = bs.id
+        LEFT JOIN rates r ON r.beatmap_id = b.id
+        LEFT JOIN beatmap_rating br ON br.rates_id = r.id
+        LEFT JOIN beatmap_mania_rating bmr ON bmr.rating_id = br.id
+        WHERE bs.osu_id = $1
+        ORDER BY b.id ASC, r.centirate ASC, r.id ASC, br.id ASC
+        "#,
+        beatmapset_osu_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let osu_ids: HashMap<i32, i32> = rows
+        .iter()
+        .filter_map(|row| row.b_osu_id.map(|osu_id| (row.b_id, osu_id)))
+        .collect();
+    Ok(group_rate_rows(rows)
+        .into_iter()
+        .filter_map(|(b_id, rates)| osu_ids.get(&b_id).map(|osu_id| (*osu_id, rates)))
+        .collect())
+}
+
+/// The given rates (by id) with their ratings, grouped by beatmap id and
+/// ascending by centirate.
+pub async fn find_rates_by_ids(
+    pool: &PgPool,
+    rate_ids: &[i32],
+) -> Result<HashMap<i32, Vec<Rates>>, sqlx::Error> {
+    if rate_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let rows = sqlx::query_as!(
+        RateDetailRow,
+        r#"
+        SELECT
+            b.id                     AS b_id,
+            b.osu_id                 AS b_osu_id,
+            b.mode                   AS "b_mode: Known<GameMode>",
+            r.id                     AS "r_id?",
+            r.osu_hash               AS "r_osu_hash?",
+            r.centirate              AS "r_centirate?",
+            r.drain_time             AS "r_drain_time?",
+            r.total_time             AS "r_total_time?",
+            r.bpm                    AS "r_bpm?",
+            br.id                    AS "br_id?",
+            br.rates_id              AS "br_rates_id?",
+            br.rating                AS "br_rating?",
+            br.rating_type           AS "br_rating_type?: Known<RatingType>",
+            bmr.id                   AS "bmr_id?",
+            bmr.stream               AS "bmr_stream?",
+            bmr.jumpstream           AS "bmr_jumpstream?",
+            bmr.handstream           AS "bmr_handstream?",
+            bmr.stamina              AS "bmr_stamina?",
+            bmr.jackspeed            AS "bmr_jackspeed?",
+            bmr.chordjack            AS "bmr_chordjack?",
+            bmr.technical            AS "bmr_technical?"
+        FROM beatmap b
+        INNER JOIN rates r ON r.beatmap_id = b.id
+        LEFT JOIN beatmap_rating br ON br.rates_id = r.id
+        LEFT JOIN beatmap_mania_rating bmr ON bmr.rating_id = br.id
+        WHERE r.id = ANY($1)
+        ORDER BY b.id ASC, r.centirate ASC, r.id ASC, br.id ASC
+        "#,
+        rate_ids
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(group_rate_rows(rows))
+}
@@ -0,0 +1,80 @@
+use crate::enums::{GameMode, Known, RatingType};
+use crate::models::rate::Rates;
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+use super::common::{group_rate_rows, RateDetailRow};
+
+/// The rate whose `.osu` file has this MD5 checksum (`rates.osu_hash`), with
+/// its ratings. Hashes of rate-changed files give that rate's centirate.
+pub async fn find_rate_by_checksum(
+    pool: &PgPool,
+    checksum: &str,
+) -> Result<Option<Rates>, sqlx::Error> {
+    let mut found = find_rates_by_checksums(pool, &[checksum]).await?;
+    Ok(found.remove(checksum).flatten())
+}
+
+/// `find_rate_by_checksum` for many checksums in one query, keyed by the
+/// checksums as given. Every requested checksum has an entry, `None` when no
+/// rate has it.
+pub async fn find_rates_by_checksums<S: AsRef<str>>(
+    pool: &PgPool,
+    checksums: &[S],
+) -> Result<HashMap<String, Option<Rates>>, sqlx::Error> {
+    if checksums.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let normalized: Vec<String> = checksums
+        .iter()
+        .map(|c| c.as_ref().trim().to_ascii_lowercase())
+        .collect();
+    let rows = sqlx::query_as!(
+        RateDetailRow,
+        r#"
+        SELECT
+            b.id                     AS b_id,
+            b.osu_id                 AS b_osu_id,
+            b.mode                   AS "b_mode: Known<GameMode>",
+            r.id                     AS "r_id?",
+            r.osu_hash               AS "r_osu_hash?",
+            r.centirate              AS "r_centirate?",
+            r.drain_time             AS "r_drain_time?",
+            r.total_time             AS "r_total_time?",
+            r.bpm                    AS "r_bpm?",
+            br.id                    AS "br_id?",
+            br.rates_id              AS "br_rates_id?",
+            br.rating                AS "br_rating?",
+            br.rating_type           AS "br_rating_type?: Known<RatingType>",
+            bmr.id                   AS "bmr_id?",
+            bmr.stream               AS "bmr_stream?",
+            bmr.jumpstream           AS "bmr_jumpstream?",
+            bmr.handstream           AS "bmr_handstream?",
+            bmr.stamina              AS "bmr_stamina?",
+            bmr.jackspeed            AS "bmr_jackspeed?",
+            bmr.chordjack            AS "bmr_chordjack?",
+            bmr.technical            AS "bmr_technical?"
+        FROM rates r
+        INNER JOIN beatmap b ON b.id = r.beatmap_id
+        LEFT JOIN beatmap_rating br ON br.rates_id = r.id
+        LEFT JOIN beatmap_mania_rating bmr ON bmr.rating_id = br.id
+        WHERE r.osu_hash = ANY($1)
+        ORDER BY r.id ASC, br.id ASC
+        "#,
+        &normalized
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let found: HashMap<String, Rates> = group_rate_rows(rows)
+        .into_values()
+        .flatten()
+        .filter_map(|rate| Some((rate.osu_hash.clone()?, rate)))
+        .collect();
+    Ok(checksums
+        .iter()
+        .zip(&normalized)
+        .map(|(checksum, hash)| (checksum.as_ref().to_string(), found.get(hash).cloned()))
+        .collect())
+}
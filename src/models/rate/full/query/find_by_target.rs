@@ -0,0 +1,182 @@
+use crate::filters::validate::DEFAULT_PER_PAGE;
+use crate::filters::{CentirateSelection, Filters, RatingFilter, ValidFilters};
+use crate::models::beatmaps::short::query::common::{apply_filters, skillset_column};
+use crate::models::rate::full::r#impl::{estimate_rate, solve_centirate, EPSILON};
+use crate::models::rate::{RateTarget, RateTargetMatch, Rates};
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+
+use super::find_all_by_beatmap::{find_rates_by_beatmap_osu_id, find_rates_by_ids};
+
+/// Lowest stored or interpolated rate of a beatmap reaching `target`, e.g.
+/// "at what rate does this map reach 28 on stream?". `None` when no rate in
+/// the stored range does.
+pub async fn find_rate_for_target_by_beatmap_osu_id(
+    pool: &PgPool,
+    beatmap_osu_id: i32,
+    target: &RateTarget,
+) -> Result<Option<Rates>, sqlx::Error> {
+    let rates = find_rates_by_beatmap_osu_id(pool, beatmap_osu_id).await?;
+    Ok(solve_centirate(&rates, target))
+}
+
+/// Beatmaps matching `filters` at any stored rate that can be rated into the
+/// band of `target`, each with its lowest rate inside it, so a map that is
+/// too easy at 1.0x but gets there at 1.2x is listed. The rating band and
+/// rate selection of `filters` are replaced by `target`; results are ordered
+/// by beatmapset then beatmap and paged by `page` / `per_page`.
+///
+/// Only the rates passing the other filters (BPM, drain time, ...) are solved
+/// over, in SQL, with the same rules as `solve_centirate`; a page is short
+/// only at the end of the results.
+pub async fn find_rates_for_target_with_filters(
+    pool: &PgPool,
+    filters: ValidFilters,
+    target: &RateTarget,
+) -> Result<Vec<RateTargetMatch>, sqlx::Error> {
+    let filters = target_filters(filters, target);
+    let rows = target_query(&filters, target)
+        .build()
+        .fetch_all(pool)
+        .await?;
+
+    let mut rate_ids: Vec<i32> = Vec::new();
+    for row in &rows {
+        rate_ids.extend(row.try_get::<Vec<i32>, _>("rate_ids")?);
+    }
+    let mut rates = find_rates_by_ids(pool, &rate_ids).await?;
+
+    let mut matches = Vec::with_capacity(rows.len());
+    for row in rows {
+        let beatmap_id: i32 = row.try_get("id")?;
+        let centirate: i32 = row.try_get("centirate")?;
+        // Only missing when the rates were deleted between the two queries
+        let Some(rate) = rates
+            .remove(&beatmap_id)
+            .and_then(|rates| estimate_rate(&rates, centirate))
+        else {
+            continue;
+        };
+        matches.push(RateTargetMatch {
+            beatmapset_osu_id: row.try_get("beatmapset_osu_id")?,
+            beatmap_osu_id: row.try_get("osu_id")?,
+            difficulty: row.try_get("difficulty")?,
+            rate,
+        });
+    }
+    Ok(matches)
+}
+
+/// `filters` with the rating band and rate selection replaced by `target`.
+fn target_filters(filters: ValidFilters, target: &RateTarget) -> Filters {
+    let mut filters = filters.into_inner();
+    filters.rating = Some(RatingFilter {
+        rating_type: Some(target.rating_type),
+        rating_min: None,
+        rating_max: None,
+    });
+    filters.rates.get_or_insert_with(Default::default).centirate = Some(CentirateSelection::Any);
+    filters
+}
+
+/// One page of `(beatmap, lowest centirate in the band, rate ids)` rows.
+fn target_query<'a>(filters: &'a Filters, target: &RateTarget) -> QueryBuilder<'a, Postgres> {
+    let per_page = filters.per_page.unwrap_or(DEFAULT_PER_PAGE);
+
+    let value = match target.skillset {
+        Some(skillset) => skillset_column(skillset),
+        None => "br.rating",
+    };
+
+    // Target value of every matching rate
+    let mut builder: QueryBuilder<Postgres> = QueryBuilder::new(
+        "WITH points AS (\n            SELECT bs.id AS set_id, b.id AS beatmap_id, r.id AS rate_id,\n                r.centirate::float8 AS c, ",
+    );
+    builder.push(value).push(
+        "::float8 AS v\n            FROM beatmapset bs\n            INNER JOIN beatmap b ON bs.id = b.beatmapset_id\n            INNER JOIN rates r ON b.id = r.beatmap_id\n            INNER JOIN beatmap_rating br ON r.id = br.rates_id\n            LEFT JOIN beatmap_mania_rating bmr ON br.id = bmr.rating_id\n            WHERE TRUE",
+    );
+    apply_filters(&mut builder, filters);
+    builder.push(" AND ").push(value).push(" IS NOT NULL");
+
+    // Each rate and the segment to the next one, linearly interpolated
+    builder.push(
+        "\n        ), segments AS (\n            SELECT *, LEAD(c) OVER w AS nc, LEAD(v) OVER w AS nv\n            FROM points WINDOW w AS (PARTITION BY beatmap_id ORDER BY c, rate_id)\n        ), candidates AS (",
+    );
+    // The stored rate when it is in the band, otherwise the lowest whole
+    // centirate strictly inside the segment whose value is
+    let lo = target.min;
+    let hi = target.max.unwrap_or(f64::INFINITY);
+    builder
+        .push("\n            SELECT s.set_id, s.beatmap_id, s.rate_id, CASE\n                WHEN s.v >= ")
+        .push_bind(lo - EPSILON)
+        .push(" AND s.v <= ")
+        .push_bind(hi + EPSILON)
+        .push(" THEN s.c\n                WHEN x.k > s.c AND x.k < s.nc AND x.k <= x.kmax THEN x.k\n            END AS centirate\n            FROM segments s\n            LEFT JOIN LATERAL (\n                SELECT CEIL(GREATEST(LEAST(t.a, t.b), s.c) - ")
+        .push_bind(EPSILON)
+        .push(") AS k, FLOOR(LEAST(GREATEST(t.a, t.b), s.nc) + ")
+        .push_bind(EPSILON)
+        .push(") AS kmax\n                FROM (SELECT s.c + (")
+        .push_bind(lo)
+        .push(" - s.v) * (s.nc - s.c) / (s.nv - s.v) AS a,\n                    s.c + (")
+        .push_bind(hi)
+        .push(" - s.v) * (s.nc - s.c) / (s.nv - s.v) AS b) t\n                WHERE s.nv IS NOT NULL AND s.nv <> s.v AND s.nc > s.c\n            ) x ON TRUE\n        )");
+
+    builder.push(
+        "\n        SELECT b.id, b.osu_id, b.difficulty, bs.osu_id AS beatmapset_osu_id,\n            MIN(cd.centirate)::int4 AS centirate, array_agg(cd.rate_id) AS rate_ids\n        FROM candidates cd\n        INNER JOIN beatmap b ON b.id = cd.beatmap_id\n        INNER JOIN beatmapset bs ON bs.id = cd.set_id\n        GROUP BY bs.id, b.id\n        HAVING MIN(cd.centirate) IS NOT NULL\n        ORDER BY bs.id, b.id LIMIT ",
+    );
+    builder
+        .push_bind(per_page as i64)
+        .push(" OFFSET ")
+        .push_bind(filters.offset());
+    builder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enums::{RatingType, Skillset};
+
+    fn target(skillset: Option<Skillset>) -> RateTarget {
+        RateTarget {
+            rating_type: RatingType::Etterna,
+            skillset,
+            min: 28.0,
+            max: None,
+        }
+    }
+
+    fn target_sql(filters: Filters, target: &RateTarget) -> String {
+        let filters = target_filters(filters.validated().unwrap(), target);
+        target_query(&filters, target).into_sql()
+    }
+
+    #[test]
+    fn target_replaces_the_rating_band_and_rate_selection() {
+        let filters = Filters::builder()
+            .rating(RatingType::Osu, Some(3.0), Some(4.0))
+            .centirate(CentirateSelection::Exact(120))
+            .bpm(Some(180.0), None)
+            .build();
+        let sql = target_sql(filters, &target(None));
+
+        assert!(sql.contains("br.rating::float8 AS v"));
+        assert!(sql.contains(" AND br.rating_type = $1 AND r.bpm >= $2 AND br.rating IS NOT NULL"));
+        assert!(!sql.contains("r.centirate ="));
+        assert!(!sql.contains("br.rating >="));
+    }
+
+    #[test]
+    fn skillset_target_solves_over_its_column() {
+        let sql = target_sql(Filters::default(), &target(Some(Skillset::Stream)));
+
+        assert!(sql.contains("bmr.stream::float8 AS v"));
+        assert!(sql.contains(" AND bmr.stream IS NOT NULL"));
+    }
+
+    #[test]
+    fn target_query_pages_by_beatmap() {
+        let filters = Filters::builder().page(2).per_page(10).build();
+        let sql = target_sql(filters, &target(None));
+
+        assert!(sql.ends_with("ORDER BY bs.id, b.id LIMIT $8 OFFSET $9"));
+    }
+}
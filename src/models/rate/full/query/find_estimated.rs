@@ -0,0 +1,17 @@
+use crate::models::rate::full::r#impl::estimate_rate;
+use crate::models::rate::Rates;
+use sqlx::PgPool;
+
+use super::find_all_by_beatmap::find_rates_by_beatmap_osu_id;
+
+/// Like `find_rate_by_beatmap_osu_id_and_centirate`, but a centirate that was
+/// not computed (e.g. 127 for 1.27x) inside the stored range is estimated
+/// from its neighbours, with `estimated: true`. `None` outside the range.
+pub async fn find_or_estimate_rate_by_beatmap_osu_id_and_centirate(
+    pool: &PgPool,
+    beatmap_osu_id: i32,
+    centirate: i32,
+) -> Result<Option<Rates>, sqlx::Error> {
+    let rates = find_rates_by_beatmap_osu_id(pool, beatmap_osu_id).await?;
+    Ok(estimate_rate(&rates, centirate))
+}
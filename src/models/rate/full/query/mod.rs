@@ -1,3 +1,12 @@
+pub(crate) mod common;
+pub mod find_all_by_beatmap;
 pub mod find_by_beatmap_and_centirate;
+pub mod find_by_checksum;
+pub mod find_by_target;
+pub mod find_estimated;
 
+pub use find_all_by_beatmap::*;
 pub use find_by_beatmap_and_centirate::*;
+pub use find_by_checksum::*;
+pub use find_by_target::*;
+pub use find_estimated::*;
@@ -1,4 +1,5 @@
-use serde::Serialize;
+use crate::enums::{RatingType, Skillset};
+use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
 #[derive(Debug, Clone, Serialize, ToSchema)]
@@ -10,6 +11,8 @@ pub struct Rates {
     pub total_time: i32,
     pub bpm: f32,
     pub rating: Vec<Rating>,
+    /// `true` when interpolated from neighbouring rates instead of computed
+    pub estimated: bool,
 }
 
 #[derive(Debug, Clone, Serialize, ToSchema)]
@@ -17,7 +20,7 @@ pub struct Rating {
     pub id: Option<i32>,
     pub rates_id: Option<i32>,
     pub rating: f64,
-    pub rating_type: String,
+    pub rating_type: RatingType,
     pub mode_rating: ModeRating,
 }
 
@@ -40,3 +43,24 @@ pub enum ModeRating {
     Ctb,
     Taiko,
 }
+
+/// Value a rate is solved for: the overall rating of `rating_type`, or one of
+/// its mania skillsets, between `min` and `max`.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+pub struct RateTarget {
+    pub rating_type: RatingType,
+    pub skillset: Option<Skillset>,
+    /// Lowest accepted value, e.g. 28 for "28 on stream"
+    pub min: f64,
+    /// Highest accepted value, open when missing
+    pub max: Option<f64>,
+}
+
+/// A beatmap of a `RateTarget` search with its lowest rate inside the band.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RateTargetMatch {
+    pub beatmapset_osu_id: Option<i32>,
+    pub beatmap_osu_id: Option<i32>,
+    pub difficulty: String,
+    pub rate: Rates,
+}